@@ -0,0 +1,180 @@
+//! send_len 吞吐量对比：旧实现（整条消息拷贝进新 Vec，再按 BUFFER_SIZE 切片 write_all）
+//! 和当前实现（头部 + 消息内容 write_vectored，不拷贝消息内容）
+//!
+//! cargo bench --bench send_len
+//! cargo bench --bench send_len --features vsock   # 额外测试 vsock，需要加载 vsock_loopback 模块
+
+use std::io::{self, Write};
+use std::net::TcpListener as StdTcpListener;
+use std::thread;
+use std::time::Instant;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+
+use tcp::frame::LengthPrefixConfig;
+use tcp::socket::{SocketAsyncSendTrait, SocketSendTrait, BUFFER_SIZE};
+
+const SIZES: [usize; 4] = [1024, 64 * 1024, 1024 * 1024, 16 * 1024 * 1024];
+
+/// 旧的 send_len 实现，用来对比
+async fn legacy_send_len<W: AsyncWrite + Unpin>(writer: &mut W, msg: &[u8]) -> io::Result<usize> {
+    let mut bytes = vec![];
+    LengthPrefixConfig::default().encode_header(msg.len(), &mut bytes)?;
+    bytes.extend_from_slice(msg);
+    for chunk in bytes.chunks(BUFFER_SIZE) {
+        writer.write_all(chunk).await?;
+    }
+    Ok(bytes.len())
+}
+
+/// 旧的 send_len 实现的 std 版本
+fn legacy_send_len_sync<W: Write>(writer: &mut W, msg: &[u8]) -> io::Result<usize> {
+    let mut bytes = vec![];
+    LengthPrefixConfig::default().encode_header(msg.len(), &mut bytes)?;
+    bytes.extend_from_slice(msg);
+    for chunk in bytes.chunks(BUFFER_SIZE) {
+        writer.write_all(chunk)?;
+    }
+    Ok(bytes.len())
+}
+
+/// 建立一条 loopback TCP 连接，服务端把收到的数据全部丢弃
+async fn tokio_tcp_pair() -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let _ = tokio::io::copy(&mut stream, &mut tokio::io::sink()).await;
+    });
+    TcpStream::connect(addr).await.unwrap()
+}
+
+fn std_tcp_pair() -> std::net::TcpStream {
+    let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = io::copy(&mut stream, &mut io::sink());
+    });
+    std::net::TcpStream::connect(addr).unwrap()
+}
+
+fn bench_tokio_tcp(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("tokio_tcp_send_len");
+    for size in SIZES {
+        let msg = vec![b'a'; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("legacy", size), &msg, |b, msg| {
+            let mut stream = rt.block_on(tokio_tcp_pair());
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        legacy_send_len(&mut stream, msg).await.unwrap();
+                    }
+                    start.elapsed()
+                })
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("vectored", size), &msg, |b, msg| {
+            let mut stream = rt.block_on(tokio_tcp_pair());
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        SocketAsyncSendTrait::send_len_bytes(&mut stream, msg).await.unwrap();
+                    }
+                    start.elapsed()
+                })
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_std_tcp(c: &mut Criterion) {
+    let mut group = c.benchmark_group("std_tcp_send_len");
+    for size in SIZES {
+        let msg = vec![b'a'; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("legacy", size), &msg, |b, msg| {
+            let mut stream = std_tcp_pair();
+            b.iter(|| legacy_send_len_sync(&mut stream, msg).unwrap());
+        });
+        group.bench_with_input(BenchmarkId::new("vectored", size), &msg, |b, msg| {
+            let mut stream = std_tcp_pair();
+            b.iter(|| SocketSendTrait::send_len_bytes(&mut stream, msg).unwrap());
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "vsock")]
+fn bench_tokio_vsock(c: &mut Criterion) {
+    use tokio_vsock::{VsockListener, VsockStream};
+
+    // VMADDR_CID_LOCAL，需要 vsock_loopback 内核模块
+    const CID_LOCAL: u32 = 1;
+    const PORT: u32 = 15000;
+
+    let rt = Runtime::new().unwrap();
+    let listener = match rt.block_on(async { VsockListener::bind(CID_LOCAL, PORT) }) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("skip vsock bench: {}", e);
+            return;
+        }
+    };
+    let listener = std::sync::Arc::new(tokio::sync::Mutex::new(listener));
+    let connect = |rt: &Runtime| -> VsockStream {
+        let listener = listener.clone();
+        rt.block_on(async move {
+            tokio::spawn(async move {
+                let (mut stream, _) = listener.lock().await.accept().await.unwrap();
+                let _ = tokio::io::copy(&mut stream, &mut tokio::io::sink()).await;
+            });
+            VsockStream::connect(CID_LOCAL, PORT).await.unwrap()
+        })
+    };
+
+    let mut group = c.benchmark_group("tokio_vsock_send_len");
+    for size in SIZES {
+        let msg = vec![b'a'; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("legacy", size), &msg, |b, msg| {
+            let mut stream = connect(&rt);
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        legacy_send_len(&mut stream, msg).await.unwrap();
+                    }
+                    start.elapsed()
+                })
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("vectored", size), &msg, |b, msg| {
+            let mut stream = connect(&rt);
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        SocketAsyncSendTrait::send_len_bytes(&mut stream, msg).await.unwrap();
+                    }
+                    start.elapsed()
+                })
+            });
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "vsock"))]
+fn bench_tokio_vsock(_c: &mut Criterion) {}
+
+criterion_group!(benches, bench_tokio_tcp, bench_std_tcp, bench_tokio_vsock);
+criterion_main!(benches);
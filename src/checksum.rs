@@ -0,0 +1,99 @@
+//! 帧校验
+//! 开启 crc32 或 xxhash feature 后可以在 send_len 格式的帧尾部附加校验值，接收方校验不一致时返回 Error::ChecksumMismatch
+//! 帧的格式为 content-length 头部 + 消息内容 + 校验值（大端），content-length 包含校验值的长度
+//! std 和 tokio 的实现格式相同，可以互相收发
+
+use crate::error::Error;
+
+/// 校验算法，收发双方必须使用相同的算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    /// CRC-32（IEEE），4 个byte
+    #[cfg(feature = "crc32")]
+    Crc32,
+    /// xxHash64（seed 为 0），8 个byte
+    #[cfg(feature = "xxhash")]
+    XxHash64,
+}
+
+impl Checksum {
+    /// 版本化帧头中的校验算法标识
+    pub fn id(self) -> u8 {
+        match self {
+            #[cfg(feature = "crc32")]
+            Checksum::Crc32 => 1,
+            #[cfg(feature = "xxhash")]
+            Checksum::XxHash64 => 2,
+        }
+    }
+
+    /// 根据标识查找校验算法，未知或者本端未开启的算法返回 None
+    pub fn from_id(id: u8) -> Option<Checksum> {
+        match id {
+            #[cfg(feature = "crc32")]
+            1 => Some(Checksum::Crc32),
+            #[cfg(feature = "xxhash")]
+            2 => Some(Checksum::XxHash64),
+            _ => None,
+        }
+    }
+
+    /// 校验值的byte数
+    pub fn size(self) -> usize {
+        match self {
+            #[cfg(feature = "crc32")]
+            Checksum::Crc32 => 4,
+            #[cfg(feature = "xxhash")]
+            Checksum::XxHash64 => 8,
+        }
+    }
+
+    /// 计算 msg 的校验值
+    pub fn compute(self, msg: &[u8]) -> u64 {
+        self.compute_parts(&[msg])
+    }
+
+    /// 计算多段数据连在一起的校验值，不需要先拼接
+    pub fn compute_parts(self, parts: &[&[u8]]) -> u64 {
+        match self {
+            #[cfg(feature = "crc32")]
+            Checksum::Crc32 => {
+                let mut hasher = crc32fast::Hasher::new();
+                parts.iter().for_each(|part| hasher.update(part));
+                hasher.finalize() as u64
+            }
+            #[cfg(feature = "xxhash")]
+            Checksum::XxHash64 => {
+                use std::hash::Hasher;
+                let mut hasher = twox_hash::XxHash64::with_seed(0);
+                parts.iter().for_each(|part| hasher.write(part));
+                hasher.finish()
+            }
+        }
+    }
+
+    /// 生成 msg 的尾部校验值
+    pub fn trailer(self, msg: &[u8]) -> Vec<u8> {
+        self.encode_value(self.compute(msg))
+    }
+
+    /// 把校验值编码为 size 个byte（大端）
+    pub fn encode_value(self, value: u64) -> Vec<u8> {
+        value.to_be_bytes()[8 - self.size()..].to_vec()
+    }
+
+    /// 校验 frame（消息内容 + 校验值），返回去掉校验值后的消息内容
+    pub fn verify(self, mut frame: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let msg_len = frame
+            .len()
+            .checked_sub(self.size())
+            .ok_or(Error::InvalidLength("frame shorter than checksum"))?;
+        let expected = frame[msg_len..].iter().fold(0u64, |value, &b| (value << 8) | b as u64);
+        frame.truncate(msg_len);
+        let actual = self.compute(&frame);
+        if expected != actual {
+            return Err(Error::ChecksumMismatch { expected, actual });
+        }
+        Ok(frame)
+    }
+}
@@ -0,0 +1,111 @@
+//! 分块传输（类似 HTTP/1.1 chunked）
+//! 长度未知的数据按块发送，每一块都是一个 send_len 格式的帧（4 个byte大端 content-length + 内容），
+//! 以一个长度为 0 的块作为结束标识。发送完成后连接仍然可以继续收发其他消息，
+//! 接收端以 AsyncRead 的形式读取，不需要把整个数据缓存在内存中
+
+use std::io::{self, ErrorKind};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use crate::error::Error;
+use crate::socket::CONTENT_LENGTH_SIZE;
+
+/// 发送时每一块的最大byte数
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// 读取块头部
+    Header,
+    /// 读取块内容，剩余的byte数
+    Body(usize),
+    /// 已读取到结束块
+    Done,
+}
+
+/// 分块数据读取器，读取到结束块后返回 EOF，不会多读后续消息
+#[derive(Debug)]
+pub struct ChunkedReader<R> {
+    inner: R,
+    state: State,
+    header: [u8; CONTENT_LENGTH_SIZE],
+    filled: usize,
+}
+
+impl<R> ChunkedReader<R> {
+    pub fn new(inner: R) -> Self {
+        ChunkedReader {
+            inner,
+            state: State::Header,
+            header: [0u8; CONTENT_LENGTH_SIZE],
+            filled: 0,
+        }
+    }
+
+    /// 是否已经读取到结束块
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> AsyncRead for ChunkedReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        loop {
+            match this.state {
+                State::Header => {
+                    //块头部可能被拆分成多个 TCP 分段，必须读满
+                    while this.filled < CONTENT_LENGTH_SIZE {
+                        let mut header = ReadBuf::new(&mut this.header[this.filled..]);
+                        ready!(Pin::new(&mut this.inner).poll_read(cx, &mut header))?;
+                        let n = header.filled().len();
+                        if n == 0 {
+                            return Poll::Ready(Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated chunked stream")));
+                        }
+                        this.filled += n;
+                    }
+                    this.filled = 0;
+                    let len = i32::from_be_bytes(this.header);
+                    let len: usize = len.try_into().map_err(|_| io::Error::from(Error::InvalidLength("negative chunk length")))?;
+                    if len == 0 {
+                        this.state = State::Done;
+                    } else {
+                        this.state = State::Body(len);
+                    }
+                }
+                State::Body(remaining) => {
+                    if buf.remaining() == 0 {
+                        return Poll::Ready(Ok(()));
+                    }
+                    //最多只读当前块剩余的byte，不能读到下一块的头部
+                    let dst = buf.initialize_unfilled_to(remaining.min(buf.remaining()));
+                    let mut body = ReadBuf::new(dst);
+                    ready!(Pin::new(&mut this.inner).poll_read(cx, &mut body))?;
+                    let n = body.filled().len();
+                    if n == 0 {
+                        return Poll::Ready(Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated chunked stream")));
+                    }
+                    buf.advance(n);
+                    this.state = if n == remaining { State::Header } else { State::Body(remaining - n) };
+                    return Poll::Ready(Ok(()));
+                }
+                State::Done => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! tokio_util::codec 编解码器
+//! 对应 socket 模块中的三种数据结束标识，可配合 Framed、FramedRead、FramedWrite 使用，
+//! 接收到的消息以 Stream 的形式返回，发送的消息通过 Sink 写入，TcpStream、VsockStream 均可使用
+
+use std::borrow::Cow;
+use std::io;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+use crate::error::Error;
+use crate::frame::{DelimiterConfig, FrameTooLarge, LengthPrefixConfig};
+
+/// 等待帧剩余内容时一次最多预留的byte数
+const MAX_RESERVE: usize = 8 * 1024;
+
+/// content-length 头部 + 消息内容
+/// 默认头部为 4 个byte大端 i32，与 send_len/read_len 的格式一致，可通过 LengthPrefixConfig 调整
+/// 帧超出 max_frame_length 时返回 FrameTooLarge，并跳过该帧剩余的内容，之后可以继续解码下一条消息
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthPrefixCodec {
+    config: LengthPrefixConfig,
+    /// 超长帧还需要跳过的byte数
+    discarding: usize,
+}
+
+impl LengthPrefixCodec {
+    pub fn new() -> Self {
+        LengthPrefixCodec::default()
+    }
+
+    pub fn with_config(config: LengthPrefixConfig) -> Self {
+        LengthPrefixCodec { config, discarding: 0 }
+    }
+
+    pub fn config(&self) -> &LengthPrefixConfig {
+        &self.config
+    }
+}
+
+impl Decoder for LengthPrefixCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        //跳过超长帧的剩余内容
+        if self.discarding > 0 {
+            let n = self.discarding.min(src.len());
+            src.advance(n);
+            self.discarding -= n;
+            if self.discarding > 0 {
+                return Ok(None);
+            }
+        }
+        let (header_len, frame_len) = match self.config.decode_header(src) {
+            Ok(Some(header)) => header,
+            Ok(None) => return Ok(None),
+            Err(err) => {
+                if let Some(FrameTooLarge { len, remaining: Some(remaining), .. }) = FrameTooLarge::from_io_error(&err) {
+                    src.advance(len - remaining);
+                    self.discarding = *remaining;
+                }
+                return Err(err);
+            }
+        };
+        if src.len() < frame_len {
+            //数据未接收完整，预留空间等待下一次读取
+            //content-length 来自对端，每次最多预留 MAX_RESERVE，缓冲区随数据到达逐步增长
+            src.reserve((frame_len - src.len()).min(MAX_RESERVE));
+            return Ok(None);
+        }
+        let mut frame = src.split_to(frame_len);
+        frame.advance(self.config.strip_len(header_len));
+        Ok(Some(frame.freeze()))
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            //对端关闭连接时消息不完整
+            None => {
+                let expected = match self.config.decode_header(src) {
+                    Ok(Some((_, frame_len))) => frame_len,
+                    _ => self.config.min_header_len(),
+                };
+                Err(Error::TruncatedFrame { expected, received: src.len() }.into())
+            }
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for LengthPrefixCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let msg = item.as_ref();
+        dst.reserve(self.config.min_header_len() + msg.len());
+        self.config.encode_header(msg.len(), dst)?;
+        dst.put_slice(msg);
+        Ok(())
+    }
+}
+
+/// 任意byte序列作为结束标识，精确匹配，设置转义字符后消息内容中可以包含结束标识
+/// 超出 max_frame_length 时返回 FrameTooLarge，此时无法确定消息边界，应关闭连接
+#[derive(Debug, Clone, Default)]
+pub struct DelimiterCodec {
+    config: DelimiterConfig,
+    /// 下一次查找结束标识的起始位置，避免重复扫描
+    next_index: usize,
+}
+
+impl DelimiterCodec {
+    pub fn new(config: DelimiterConfig) -> Self {
+        DelimiterCodec { config, next_index: 0 }
+    }
+
+    pub fn config(&self) -> &DelimiterConfig {
+        &self.config
+    }
+
+    fn max_length(&self) -> usize {
+        self.config.max_frame_length.unwrap_or(usize::MAX)
+    }
+}
+
+impl Decoder for DelimiterCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        let delimiter_len = self.config.delimiter.len();
+        let max_length = self.max_length();
+        match self.config.find_delimiter(src, self.next_index) {
+            Ok(end) if end <= max_length => {
+                self.next_index = 0;
+                let frame = src.split_to(end + delimiter_len);
+                let msg = match self.config.unescape_payload(&frame[..end]) {
+                    //没有转义字符，直接复用缓冲区
+                    Cow::Borrowed(_) => {
+                        let mut frame = frame;
+                        if !self.config.keep_delimiter {
+                            frame.truncate(end);
+                        }
+                        return Ok(Some(frame.freeze()));
+                    }
+                    Cow::Owned(msg) => msg,
+                };
+                let mut msg = BytesMut::from(&msg[..]);
+                if self.config.keep_delimiter {
+                    msg.extend_from_slice(&frame[end..]);
+                }
+                Ok(Some(msg.freeze()))
+            }
+            Ok(end) => Err(FrameTooLarge::new(end, max_length, None).into()),
+            Err(_) if src.len() > max_length.saturating_add(delimiter_len - 1) => {
+                Err(FrameTooLarge::new(src.len(), max_length, None).into())
+            }
+            Err(next_index) => {
+                self.next_index = next_index;
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        match self.decode(src)? {
+            Some(msg) => Ok(Some(msg)),
+            None if src.is_empty() => Ok(None),
+            None if src.len() > self.max_length() => {
+                Err(FrameTooLarge::new(src.len(), self.max_length(), None).into())
+            }
+            //对端关闭连接时，剩余数据作为最后一条消息
+            None => {
+                self.next_index = 0;
+                let msg = src.split().freeze();
+                match self.config.unescape_payload(&msg) {
+                    Cow::Borrowed(_) => Ok(Some(msg)),
+                    Cow::Owned(msg) => Ok(Some(Bytes::from(msg))),
+                }
+            }
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for DelimiterCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), io::Error> {
+        let msg = self.config.escape_payload(item.as_ref());
+        dst.reserve(msg.len() + self.config.delimiter.len());
+        dst.put_slice(&msg);
+        dst.put_slice(&self.config.delimiter);
+        Ok(())
+    }
+}
+
+/// 空行（\n\n）作为结束标识
+/// 与 send_line/read_line 的格式一致，只精确匹配 \n\n
+#[derive(Debug, Clone, Default)]
+pub struct BlankLineCodec(DelimiterCodec);
+
+impl BlankLineCodec {
+    pub fn new() -> Self {
+        BlankLineCodec::default()
+    }
+
+    /// 限制单条消息的最大长度，超出时返回 FrameTooLarge
+    /// 此时无法确定消息边界，应关闭连接
+    pub fn with_max_length(max_length: usize) -> Self {
+        BlankLineCodec(DelimiterCodec::new(DelimiterConfig::blank_line().max_frame_length(max_length)))
+    }
+}
+
+impl Decoder for BlankLineCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        self.0.decode(src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        self.0.decode_eof(src)
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for BlankLineCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), io::Error> {
+        self.0.encode(item, dst)
+    }
+}
+
+/// 写通道关闭（read 返回 0）作为结束标识
+/// 与 send/recv 的格式一致，一个连接只能接收一条消息
+#[derive(Debug, Clone, Copy)]
+pub struct EofCodec {
+    /// 消息允许的最大byte数
+    max_length: usize,
+}
+
+impl Default for EofCodec {
+    fn default() -> Self {
+        EofCodec { max_length: usize::MAX }
+    }
+}
+
+impl EofCodec {
+    pub fn new() -> Self {
+        EofCodec::default()
+    }
+
+    /// 限制消息的最大长度，超出时返回 FrameTooLarge
+    pub fn with_max_length(max_length: usize) -> Self {
+        EofCodec { max_length }
+    }
+}
+
+impl Decoder for EofCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        if src.len() > self.max_length {
+            return Err(FrameTooLarge::new(src.len(), self.max_length, None).into());
+        }
+        //没有结束标识，一直缓存到 EOF
+        Ok(None)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        self.decode(src)?;
+        if src.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(src.split().freeze()))
+    }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for EofCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), io::Error> {
+        dst.extend_from_slice(item.as_ref());
+        Ok(())
+    }
+}
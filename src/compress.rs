@@ -0,0 +1,166 @@
+//! 按帧压缩
+//! 压缩帧的格式为 content-length 头部 + 1 个byte压缩算法标识 + 消息内容（压缩后或原样），
+//! 接收方根据标识自动解压，发送方可以按消息大小决定是否压缩
+//! 开启 zstd、gzip、lz4 feature 后可以使用对应的压缩算法，未开启时只能收发未压缩的帧
+
+use std::borrow::Cow;
+use std::io::{self, Read};
+use crate::error::Error;
+use crate::frame::FrameTooLarge;
+
+/// 默认的压缩阈值，小于该值的消息不压缩
+pub const DEFAULT_COMPRESS_THRESHOLD: usize = 1024;
+/// 默认的最大解压长度
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+/// 压缩算法，标识写在帧中，取值固定不随 feature 变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// 不压缩
+    #[default]
+    None,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+impl Compression {
+    /// 帧中的标识
+    pub fn id(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 1,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => 2,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => 3,
+        }
+    }
+
+    /// 根据帧中的标识查找压缩算法，未知或者本端未开启的算法返回 None
+    pub fn from_id(id: u8) -> Option<Compression> {
+        match id {
+            0 => Some(Compression::None),
+            #[cfg(feature = "zstd")]
+            1 => Some(Compression::Zstd),
+            #[cfg(feature = "gzip")]
+            2 => Some(Compression::Gzip),
+            #[cfg(feature = "lz4")]
+            3 => Some(Compression::Lz4),
+            _ => None,
+        }
+    }
+
+    fn compress(self, msg: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(msg.to_vec()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => zstd::bulk::compress(msg, zstd::DEFAULT_COMPRESSION_LEVEL),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                use std::io::Write;
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(msg)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                use std::io::Write;
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+                encoder.write_all(msg)?;
+                encoder.finish().map_err(io::Error::from)
+            }
+        }
+    }
+
+    /// 解压 payload，最多输出 max 个byte，超出时返回 FrameTooLarge
+    fn decompress(self, payload: &[u8], max: usize) -> Result<Vec<u8>, Error> {
+        match self {
+            Compression::None => read_limited(payload, max),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => read_limited(zstd::stream::read::Decoder::with_buffer(payload).map_err(Error::Decompress)?, max),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => read_limited(flate2::read::GzDecoder::new(payload), max),
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => read_limited(lz4_flex::frame::FrameDecoder::new(payload), max),
+        }
+    }
+}
+
+/// 读取全部数据，超过 max 个byte时立即停止，防止压缩炸弹
+fn read_limited<R: Read>(reader: R, max: usize) -> Result<Vec<u8>, Error> {
+    let mut msg = vec![];
+    let limit = (max as u64).saturating_add(1);
+    let n = reader.take(limit).read_to_end(&mut msg).map_err(Error::Decompress)?;
+    if n > max {
+        //帧已经完整读取，连接仍然可用
+        return Err(FrameTooLarge::new(n, max, Some(0)).into());
+    }
+    Ok(msg)
+}
+
+/// 压缩设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionConfig {
+    /// 发送时使用的压缩算法，接收时以帧中的标识为准
+    pub compression: Compression,
+    /// 小于该长度的消息不压缩
+    pub threshold: usize,
+    /// 解压后允许的最大长度
+    pub max_decompressed_size: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig::new(Compression::None)
+    }
+}
+
+impl CompressionConfig {
+    pub fn new(compression: Compression) -> Self {
+        CompressionConfig {
+            compression,
+            threshold: DEFAULT_COMPRESS_THRESHOLD,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn max_decompressed_size(mut self, max: usize) -> Self {
+        self.max_decompressed_size = max;
+        self
+    }
+
+    /// 按设置压缩消息，返回实际使用的压缩算法和帧中的消息内容
+    /// 消息小于阈值或者压缩后没有变小时原样返回
+    pub fn compress<'a>(&self, msg: &'a [u8]) -> Result<(Compression, Cow<'a, [u8]>), Error> {
+        if self.compression == Compression::None || msg.len() < self.threshold {
+            return Ok((Compression::None, Cow::Borrowed(msg)));
+        }
+        let compressed = self.compression.compress(msg)?;
+        if compressed.len() >= msg.len() {
+            return Ok((Compression::None, Cow::Borrowed(msg)));
+        }
+        Ok((self.compression, Cow::Owned(compressed)))
+    }
+
+    /// 解析压缩帧（标识 + 消息内容）并解压
+    /// 未知的压缩算法和损坏的数据返回 Error::Decompress，解压后超出 max_decompressed_size 返回 Error::FrameTooLarge
+    pub fn decompress(&self, frame: &[u8]) -> Result<Vec<u8>, Error> {
+        let (&id, payload) = frame
+            .split_first()
+            .ok_or_else(|| Error::Decompress(io::Error::new(io::ErrorKind::InvalidData, "missing compression flag")))?;
+        let compression = Compression::from_id(id).ok_or_else(|| {
+            Error::Decompress(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported compression: {}", id)))
+        })?;
+        compression.decompress(payload, self.max_decompressed_size)
+    }
+}
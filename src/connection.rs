@@ -0,0 +1,238 @@
+//! 带设置的连接
+//! Connection 在 MessageReader 的基础上保存该连接使用的消息格式，send_msg/recv_msg 按连接的设置收发，
+//! 同一个程序中不同的连接可以使用不同的格式，TcpStream、VsockStream 均可使用
+//! 设置了压缩后 send_msg/recv_msg 按 send_compressed/read_compressed 的格式收发，收发双方的设置必须一致
+//! 设置了校验后在帧尾部附加校验值（send_len_checked 的格式），同时设置压缩时校验值覆盖压缩后的内容
+//! 通过 Connection::client/Connection::server 建立连接时先进行握手，协商出的设置保存在连接上
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(any(feature = "crc32", feature = "xxhash"))]
+use crate::checksum::Checksum;
+use crate::compress::{Compression, CompressionConfig};
+use crate::error::Error;
+use crate::handshake::{self, Framing, Handshake, Settings};
+use crate::message::Format;
+use crate::reader::MessageReader;
+use crate::socket::SocketAsyncSendTrait;
+use crate::versioned::{Frame, FrameOptions, DEFAULT_MAX_FRAME_LENGTH};
+
+#[derive(Debug)]
+pub struct Connection<S> {
+    reader: MessageReader<S>,
+    format: Format,
+    framing: Framing,
+    compression: Option<CompressionConfig>,
+    settings: Option<Settings>,
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    checksum: Option<Checksum>,
+}
+
+impl<S> Connection<S> {
+    /// 使用默认格式（JSON）
+    /// 单条消息默认最多 versioned::DEFAULT_MAX_FRAME_LENGTH 个byte，握手后使用协商出的最大长度
+    pub fn new(stream: S) -> Self {
+        Connection::with_format(stream, Format::default())
+    }
+
+    pub fn with_format(stream: S, format: Format) -> Self {
+        Connection {
+            reader: MessageReader::new(stream),
+            format,
+            framing: Framing::default(),
+            compression: None,
+            settings: None,
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            checksum: None,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: Format) {
+        self.format = format;
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// 设置 send_frame/recv_frame 的分帧方式，默认 Framing::Len
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    /// 握手协商出的设置，没有握手时为 None
+    pub fn settings(&self) -> Option<&Settings> {
+        self.settings.as_ref()
+    }
+
+    /// 应用握手协商出的设置：分帧方式、格式、压缩和最大帧长度
+    fn apply(&mut self, settings: Settings) {
+        self.framing = settings.framing;
+        self.format = settings.format;
+        self.compression = if settings.compression == Compression::None {
+            None
+        } else {
+            Some(CompressionConfig::new(settings.compression).max_decompressed_size(settings.max_frame_length))
+        };
+        self.reader.set_max_frame_length(settings.max_frame_length);
+        self.settings = Some(settings);
+    }
+
+    pub fn compression(&self) -> Option<&CompressionConfig> {
+        self.compression.as_ref()
+    }
+
+    /// None 表示不压缩，帧中也不包含压缩算法标识
+    pub fn set_compression(&mut self, compression: Option<CompressionConfig>) {
+        self.compression = compression;
+    }
+
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    pub fn checksum(&self) -> Option<Checksum> {
+        self.checksum
+    }
+
+    /// None 表示不校验，帧中也不包含校验值
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    pub fn set_checksum(&mut self, checksum: Option<Checksum>) {
+        self.checksum = checksum;
+    }
+
+    /// 底层的 MessageReader，可以用来按其他方式收发消息
+    pub fn reader(&self) -> &MessageReader<S> {
+        &self.reader
+    }
+
+    pub fn reader_mut(&mut self) -> &mut MessageReader<S> {
+        &mut self.reader
+    }
+
+    pub fn get_ref(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        self.reader.get_mut()
+    }
+
+    /// 返回底层连接，缓冲区中未消费的数据会被丢弃
+    pub fn into_inner(self) -> S {
+        self.reader.into_inner()
+    }
+}
+
+impl<S> Connection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// 客户端建立连接：发送本端支持的设置，按服务端的回复设置连接
+    pub async fn client(stream: S, handshake: &Handshake) -> Result<Self, Error> {
+        let mut conn = Connection::new(stream);
+        let settings = handshake::client(&mut conn.reader, handshake).await?;
+        conn.apply(settings);
+        Ok(conn)
+    }
+
+    /// 服务端建立连接：读取客户端支持的设置，协商后回复并设置连接
+    pub async fn server(stream: S, handshake: &Handshake) -> Result<Self, Error> {
+        let mut conn = Connection::new(stream);
+        let settings = handshake::server(&mut conn.reader, handshake).await?;
+        conn.apply(settings);
+        Ok(conn)
+    }
+
+    /// 按连接的格式序列化 msg 后发送
+    pub async fn send_msg<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized,
+    {
+        let msg = self.format.encode(msg)?;
+        self.send_frame(&msg).await
+    }
+
+    /// 按连接的格式读取一条消息，与 MessageReader 一样是取消安全的
+    pub async fn recv_msg<M>(&mut self) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        let msg = self.recv_frame().await?;
+        self.format.decode(&msg)
+    }
+
+    /// 按连接的分帧方式发送一条消息
+    /// Framing::Line 不使用压缩和校验，消息中不能包含空行
+    pub async fn send_frame(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        match self.framing {
+            Framing::Len => self.send_len_frame(msg).await,
+            Framing::Line => self.reader.send_line_bytes(msg).await,
+            Framing::Versioned => {
+                let options = self.frame_options();
+                self.reader.send_versioned(&Frame::new(0, msg.to_vec()), &options).await
+            }
+        }
+    }
+
+    /// 按连接的分帧方式读取一条消息
+    pub async fn recv_frame(&mut self) -> Result<Vec<u8>, Error> {
+        match self.framing {
+            Framing::Len => self.recv_len_frame().await,
+            Framing::Line => self.reader.read_line_bytes().await,
+            Framing::Versioned => {
+                let options = self.frame_options();
+                Ok(self.reader.read_versioned(&options).await?.payload)
+            }
+        }
+    }
+
+    async fn send_len_frame(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        #[cfg(any(feature = "crc32", feature = "xxhash"))]
+        if let Some(checksum) = self.checksum {
+            let frame = match &self.compression {
+                Some(config) => {
+                    //压缩算法标识 + 压缩后的内容，校验值覆盖整个帧
+                    let (compression, payload) = config.compress(msg)?;
+                    let mut frame = Vec::with_capacity(payload.len() + 1);
+                    frame.push(compression.id());
+                    frame.extend_from_slice(&payload);
+                    frame
+                }
+                None => msg.to_vec(),
+            };
+            return self.reader.send_len_checked(&frame, checksum).await;
+        }
+        match &self.compression {
+            Some(config) => self.reader.send_compressed(msg, config).await,
+            None => self.reader.send_len_bytes(msg).await,
+        }
+    }
+
+    async fn recv_len_frame(&mut self) -> Result<Vec<u8>, Error> {
+        #[cfg(any(feature = "crc32", feature = "xxhash"))]
+        if let Some(checksum) = self.checksum {
+            let frame = self.reader.read_len_checked(checksum).await?;
+            return match &self.compression {
+                Some(config) => config.decompress(&frame),
+                None => Ok(frame),
+            };
+        }
+        match &self.compression {
+            Some(config) => self.reader.read_compressed(config).await,
+            None => self.reader.read_len_bytes().await,
+        }
+    }
+
+    /// Framing::Versioned 使用的设置
+    fn frame_options(&self) -> FrameOptions {
+        FrameOptions {
+            compression: self.compression,
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            checksum: self.checksum,
+            max_frame_length: self.settings.map_or(DEFAULT_MAX_FRAME_LENGTH, |settings| settings.max_frame_length),
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! 分帧方式自动识别
+//! 同一个端口上既有使用 send_len 的客户端，也有使用 send_line/send 的客户端时，
+//! 服务端可以先查看新连接的前几个byte，判断对端的分帧方式，再按该方式收发消息
+//!
+//! 判断顺序：
+//! 1. 配置的 magic 前缀，匹配后前缀被丢弃
+//! 2. versioned 模块的帧头（magic + 支持的主版本）
+//! 3. 前 4 个byte按 send_len 的大端 i32 解析，长度不超过 max_frame_length 时视为 content-length
+//!    文本消息的第一个byte通常不小于 0x20，解析出的长度至少 512MB，max_frame_length 不能设置得过大
+//! 4. 已经读到空行（\n\n）时为 read_line 格式，对端关闭写通道时为 recv 格式（读到 EOF）
+//! 5. 读取 max_sniff_len 个byte后仍无法判断时使用 text_fallback
+//!
+//! 查看的数据保留在 MessageReader 的缓冲区中，不会丢失
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use crate::error::Error;
+use crate::reader::MessageReader;
+use crate::socket::{SocketAsyncSendTrait, CONTENT_LENGTH_SIZE};
+use crate::versioned::{Frame, FrameOptions, DEFAULT_MAX_FRAME_LENGTH, MAGIC, VERSION_MAJOR};
+
+/// 默认最多查看的byte数
+pub const DEFAULT_MAX_SNIFF_LEN: usize = 64 * 1024;
+
+/// 识别出的分帧方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedFraming {
+    /// send_len/read_len
+    Len,
+    /// send_line/read_line
+    Line,
+    /// send + 关闭写通道/recv
+    Eof,
+    /// send_versioned/read_versioned
+    Versioned,
+}
+
+/// 识别设置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectConfig {
+    /// 客户端在第一条消息之前发送的 magic 前缀，按顺序匹配
+    pub prefixes: Vec<(Vec<u8>, DetectedFraming)>,
+    /// 单条消息允许的最大byte数，同时用于判断 content-length 是否合理
+    pub max_frame_length: usize,
+    /// 最多查看的byte数
+    pub max_sniff_len: usize,
+    /// 查看 max_sniff_len 个byte后仍无法判断时使用的方式
+    pub text_fallback: DetectedFraming,
+}
+
+impl Default for DetectConfig {
+    fn default() -> Self {
+        DetectConfig {
+            prefixes: vec![],
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            max_sniff_len: DEFAULT_MAX_SNIFF_LEN,
+            text_fallback: DetectedFraming::Line,
+        }
+    }
+}
+
+impl DetectConfig {
+    /// 添加 magic 前缀
+    pub fn prefix(mut self, magic: impl Into<Vec<u8>>, framing: DetectedFraming) -> Self {
+        self.prefixes.push((magic.into(), framing));
+        self
+    }
+
+    pub fn max_frame_length(mut self, max: usize) -> Self {
+        self.max_frame_length = max;
+        self
+    }
+
+    pub fn max_sniff_len(mut self, max: usize) -> Self {
+        self.max_sniff_len = max;
+        self
+    }
+
+    pub fn text_fallback(mut self, framing: DetectedFraming) -> Self {
+        self.text_fallback = framing;
+        self
+    }
+
+    /// 根据已经读到的数据判断分帧方式，返回分帧方式和需要丢弃的前缀长度，数据不足以判断时返回 None
+    pub fn classify(&self, buf: &[u8], eof: bool) -> Option<(DetectedFraming, usize)> {
+        for (magic, framing) in &self.prefixes {
+            if buf.starts_with(magic) {
+                return Some((*framing, magic.len()));
+            }
+            if !eof && magic.starts_with(buf) {
+                return None;
+            }
+        }
+        let versioned = [MAGIC[0], MAGIC[1], VERSION_MAJOR << 4];
+        if buf.len() >= versioned.len() && buf[..2] == MAGIC && buf[2] >> 4 == VERSION_MAJOR {
+            return Some((DetectedFraming::Versioned, 0));
+        }
+        if !eof && buf.len() < versioned.len() && versioned[..buf.len()] == *buf {
+            return None;
+        }
+        if buf.len() >= CONTENT_LENGTH_SIZE {
+            if self.is_content_length(&buf[..CONTENT_LENGTH_SIZE]) {
+                return Some((DetectedFraming::Len, 0));
+            }
+        } else if !eof && self.is_content_length(buf) {
+            //可能是 content-length 的开头，等待更多数据
+            return None;
+        }
+        if buf.windows(2).any(|w| w == b"\n\n") {
+            return Some((DetectedFraming::Line, 0));
+        }
+        if eof {
+            return Some((DetectedFraming::Eof, 0));
+        }
+        if buf.len() >= self.max_sniff_len {
+            return Some((self.text_fallback, 0));
+        }
+        None
+    }
+
+    /// header 是否可能是合理的 content-length，不足 4 个byte时按剩余byte为 0 计算
+    fn is_content_length(&self, header: &[u8]) -> bool {
+        let mut bytes = [0u8; CONTENT_LENGTH_SIZE];
+        bytes[..header.len()].copy_from_slice(header);
+        let len = i32::from_be_bytes(bytes);
+        len >= 0 && (len as usize).saturating_add(CONTENT_LENGTH_SIZE) <= self.max_frame_length
+    }
+}
+
+/// 查看新连接的前几个byte，返回已经按识别结果设置好的读取器
+/// 对端没有发送任何数据就关闭连接时返回 Error::PeerClosed
+pub async fn detect<S>(stream: S, config: &DetectConfig) -> Result<DetectedReader<S>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut reader = MessageReader::new(stream);
+    reader.set_max_frame_length(config.max_frame_length);
+    let mut eof = false;
+    let framing = loop {
+        if eof && reader.buffer().is_empty() {
+            return Err(Error::PeerClosed);
+        }
+        if let Some((framing, prefix_len)) = config.classify(reader.buffer(), eof) {
+            reader.consume(prefix_len);
+            break framing;
+        }
+        eof = reader.fill_buffer().await? == 0;
+    };
+    Ok(DetectedReader {
+        reader,
+        framing,
+        options: FrameOptions::default().max_frame_length(config.max_frame_length),
+    })
+}
+
+/// 按识别出的分帧方式收发消息的读取器
+#[derive(Debug)]
+pub struct DetectedReader<S> {
+    reader: MessageReader<S>,
+    framing: DetectedFraming,
+    options: FrameOptions,
+}
+
+impl<S> DetectedReader<S> {
+    pub fn framing(&self) -> DetectedFraming {
+        self.framing
+    }
+
+    pub fn reader(&self) -> &MessageReader<S> {
+        &self.reader
+    }
+
+    pub fn reader_mut(&mut self) -> &mut MessageReader<S> {
+        &mut self.reader
+    }
+
+    /// 返回底层的 MessageReader，缓冲区中的数据保留
+    pub fn into_inner(self) -> MessageReader<S> {
+        self.reader
+    }
+}
+
+impl<S> DetectedReader<S>
+where
+    S: AsyncRead + Unpin,
+{
+    /// 按识别出的分帧方式读取一条消息
+    /// DetectedFraming::Eof 读取到对端关闭写通道为止，之后再调用返回空消息
+    pub async fn recv_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        match self.framing {
+            DetectedFraming::Len => self.reader.read_len_bytes().await,
+            DetectedFraming::Line => self.reader.read_line_bytes().await,
+            DetectedFraming::Eof => self.reader.recv_bytes().await,
+            DetectedFraming::Versioned => Ok(self.reader.read_versioned(&self.options).await?.payload),
+        }
+    }
+
+    /// recv_bytes 的 String 版本，按 MessageReader 的 utf8_mode 转换
+    pub async fn recv(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        self.reader.utf8_mode().decode(msg)
+    }
+}
+
+impl<S> DetectedReader<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// 按识别出的分帧方式回复一条消息
+    /// DetectedFraming::Eof 直接发送消息内容，需要由调用方关闭写通道
+    pub async fn send_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        match self.framing {
+            DetectedFraming::Len => self.reader.send_len_bytes(msg).await,
+            DetectedFraming::Line => self.reader.send_line_bytes(msg).await,
+            DetectedFraming::Eof => SocketAsyncSendTrait::send_bytes(&mut self.reader, msg).await,
+            DetectedFraming::Versioned => {
+                self.reader.send_versioned(&Frame::new(0, msg.to_vec()), &self.options).await
+            }
+        }
+    }
+
+    pub async fn send(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_bytes(msg.as_bytes()).await
+    }
+}
@@ -0,0 +1,189 @@
+//! 统一错误类型
+//! socket/vsock 的 Trait 方法和 MessageReader 返回 tcp::Error，调用方可以直接 match 失败原因，不需要解析错误信息
+//! codec 中的编解码器按 tokio_util 的约定仍然返回 io::Error，通过 Error::from 可以还原为对应的变体
+
+use std::{error, fmt};
+use std::io::{self, ErrorKind};
+use crate::frame::FrameTooLarge;
+#[cfg(feature = "encoding")]
+use crate::text::InvalidEncoding;
+use crate::text::InvalidUtf8;
+
+#[derive(Debug)]
+pub enum Error {
+    /// 对端在消息开始之前关闭了连接（没有读到任何头部数据）
+    PeerClosed,
+    /// 消息没有接收完整对端就关闭了连接
+    TruncatedFrame {
+        /// 该帧应有的byte数（包含头部）
+        expected: usize,
+        /// 实际收到的byte数
+        received: usize,
+    },
+    /// 消息超出最大长度限制
+    FrameTooLarge(FrameTooLarge),
+    /// 头部中的 content-length 非法（负数、溢出、小于头部长度等）
+    InvalidLength(&'static str),
+    /// 版本化帧的头部非法（magic 不一致、未知的标志位等）
+    InvalidHeader(&'static str),
+    /// 版本化帧的主版本不支持
+    UnsupportedVersion {
+        major: u8,
+        minor: u8,
+    },
+    /// 握手失败（没有共同的设置、对端拒绝、对端没有发送握手消息等）
+    #[cfg(feature = "serde")]
+    Handshake(String),
+    /// *_timeout、*_deadline 或 TimeoutStream 设置的超时时间已到
+    Timeout,
+    /// 消息不是合法的 UTF-8
+    InvalidUtf8(InvalidUtf8),
+    /// 消息不是指定编码的合法数据
+    #[cfg(feature = "encoding")]
+    InvalidEncoding(InvalidEncoding),
+    /// 消息序列化失败
+    #[cfg(any(feature = "serde", feature = "prost"))]
+    Serialize(Box<dyn error::Error + Send + Sync>),
+    /// 收到的消息无法反序列化为指定类型，可以 downcast 为具体格式的错误（例如 serde_json::Error）
+    #[cfg(any(feature = "serde", feature = "prost"))]
+    Deserialize(Box<dyn error::Error + Send + Sync>),
+    /// 帧尾部的校验值与收到的消息内容不一致，数据在传输中被破坏
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    ChecksumMismatch {
+        /// 帧中携带的校验值
+        expected: u64,
+        /// 按收到的消息内容计算的校验值
+        actual: u64,
+    },
+    /// 压缩帧无法解压（数据损坏或者使用了本端未开启的压缩算法）
+    Decompress(io::Error),
+    /// 其他 io 错误
+    Io(io::Error),
+}
+
+impl Error {
+    /// 对应的 io::ErrorKind，转换为 io::Error 时使用
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::PeerClosed | Error::TruncatedFrame { .. } => ErrorKind::UnexpectedEof,
+            #[cfg(any(feature = "serde", feature = "prost"))]
+            Error::Serialize(_) => ErrorKind::InvalidInput,
+            Error::Timeout => ErrorKind::TimedOut,
+            Error::Io(e) => e.kind(),
+            _ => ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PeerClosed => write!(f, "peer closed connection before content-length"),
+            Error::TruncatedFrame { expected, received } => {
+                write!(f, "truncated frame: expected {} bytes, received {}", expected, received)
+            }
+            Error::FrameTooLarge(e) => e.fmt(f),
+            Error::InvalidLength(reason) => write!(f, "invalid content-length: {}", reason),
+            Error::InvalidHeader(reason) => write!(f, "invalid frame header: {}", reason),
+            Error::UnsupportedVersion { major, minor } => write!(f, "unsupported frame version {}.{}", major, minor),
+            #[cfg(feature = "serde")]
+            Error::Handshake(reason) => write!(f, "handshake failed: {}", reason),
+            Error::Timeout => write!(f, "timed out"),
+            Error::InvalidUtf8(e) => e.fmt(f),
+            #[cfg(feature = "encoding")]
+            Error::InvalidEncoding(e) => e.fmt(f),
+            #[cfg(any(feature = "serde", feature = "prost"))]
+            Error::Serialize(e) => write!(f, "serialize message: {}", e),
+            #[cfg(any(feature = "serde", feature = "prost"))]
+            Error::Deserialize(e) => write!(f, "deserialize message: {}", e),
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            Error::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: expected {:#x}, actual {:#x}", expected, actual)
+            }
+            Error::Decompress(e) => write!(f, "decompress frame: {}", e),
+            Error::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::FrameTooLarge(e) => Some(e),
+            Error::InvalidUtf8(e) => Some(e),
+            #[cfg(feature = "encoding")]
+            Error::InvalidEncoding(e) => Some(e),
+            #[cfg(any(feature = "serde", feature = "prost"))]
+            Error::Serialize(e) | Error::Deserialize(e) => Some(e.as_ref()),
+            Error::Decompress(e) | Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// 从 io::Error 中还原具体的错误类型，无法识别的保留为 Error::Io
+/// 只有本库产生的超时还原为 Error::Timeout，操作系统返回的 TimedOut（例如 TCP 重传超时）保留为 Error::Io
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        if !err.get_ref().is_some_and(is_typed) {
+            return Error::Io(err);
+        }
+        let inner = err.into_inner().unwrap();
+        let inner = match inner.downcast::<Error>() {
+            Ok(e) => return *e,
+            Err(inner) => inner,
+        };
+        let inner = match inner.downcast::<FrameTooLarge>() {
+            Ok(e) => return Error::FrameTooLarge(*e),
+            Err(inner) => inner,
+        };
+        #[cfg(feature = "encoding")]
+        let inner = match inner.downcast::<InvalidEncoding>() {
+            Ok(e) => return Error::InvalidEncoding(*e),
+            Err(inner) => inner,
+        };
+        Error::InvalidUtf8(*inner.downcast::<InvalidUtf8>().unwrap())
+    }
+}
+
+/// io::Error 中携带的是否为本库的错误类型
+fn is_typed(err: &(dyn error::Error + Send + Sync + 'static)) -> bool {
+    #[cfg(feature = "encoding")]
+    if err.is::<InvalidEncoding>() {
+        return true;
+    }
+    err.is::<Error>() || err.is::<FrameTooLarge>() || err.is::<InvalidUtf8>()
+}
+
+/// 转换为 io::Error，FrameTooLarge 等仍然可以通过各自的 from_io_error 识别
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(e) => e,
+            Error::FrameTooLarge(e) => e.into(),
+            Error::InvalidUtf8(e) => e.into(),
+            #[cfg(feature = "encoding")]
+            Error::InvalidEncoding(e) => e.into(),
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+impl From<FrameTooLarge> for Error {
+    fn from(err: FrameTooLarge) -> Self {
+        Error::FrameTooLarge(err)
+    }
+}
+
+impl From<InvalidUtf8> for Error {
+    fn from(err: InvalidUtf8) -> Self {
+        Error::InvalidUtf8(err)
+    }
+}
+
+#[cfg(feature = "encoding")]
+impl From<InvalidEncoding> for Error {
+    fn from(err: InvalidEncoding) -> Self {
+        Error::InvalidEncoding(err)
+    }
+}
@@ -0,0 +1,462 @@
+//! 帧格式配置
+//! send_len/read_len 默认使用 4 个byte大端 i32 作为 content-length 头部，
+//! 与其他语言/框架对接时可以通过 LengthPrefixConfig 调整头部格式
+
+use std::{error, fmt};
+use std::borrow::Cow;
+use std::io::{self, ErrorKind};
+use bytes::BufMut;
+use crate::error::Error;
+
+/// varint（LEB128）最多占用的byte数
+const VARINT_MAX_SIZE: usize = 10;
+
+/// content-length 字段的宽度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthWidth {
+    U8,
+    U16,
+    U24,
+    U32,
+    U64,
+    /// LEB128 varint，1~10 个byte，不区分大小端
+    Varint,
+}
+
+impl LengthWidth {
+    /// 固定宽度的byte数，varint 返回 None
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            LengthWidth::U8 => Some(1),
+            LengthWidth::U16 => Some(2),
+            LengthWidth::U24 => Some(3),
+            LengthWidth::U32 => Some(4),
+            LengthWidth::U64 => Some(8),
+            LengthWidth::Varint => None,
+        }
+    }
+}
+
+/// content-length 字段的字节序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// content-length 头部格式
+///
+/// 帧结构（与 Netty LengthFieldBasedFrameDecoder 一致）：
+/// `[length_field_offset 个byte][content-length][内容]`
+/// 整个帧的长度 = length_field_offset + content-length 字段宽度 + content-length 值 + length_adjustment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthPrefixConfig {
+    /// content-length 字段宽度
+    pub width: LengthWidth,
+    /// content-length 字段字节序，varint 忽略该配置
+    pub endian: Endian,
+    /// content-length 是否为有符号数（例如 Java 的 int），最高位为 1 视为非法长度
+    pub signed: bool,
+    /// content-length 字段之前的byte数，发送时填充 0
+    pub length_field_offset: usize,
+    /// content-length 值的修正量，例如 content-length 包含头部长度时为负数
+    pub length_adjustment: isize,
+    /// 接收时从帧开头去掉的byte数，None 表示去掉整个头部只返回内容
+    pub initial_bytes_to_strip: Option<usize>,
+    /// 整个帧（包含头部）允许的最大byte数，None 表示不限制
+    /// 在分配内存之前检查，超出时返回 FrameTooLarge
+    pub max_frame_length: Option<usize>,
+}
+
+impl Default for LengthPrefixConfig {
+    /// 4 个byte大端 i32，与 send_len/read_len 的默认格式一致
+    fn default() -> Self {
+        LengthPrefixConfig {
+            width: LengthWidth::U32,
+            endian: Endian::Big,
+            signed: true,
+            length_field_offset: 0,
+            length_adjustment: 0,
+            initial_bytes_to_strip: None,
+            max_frame_length: None,
+        }
+    }
+}
+
+impl LengthPrefixConfig {
+    /// Java DataOutputStream.writeInt / DataInputStream.readInt
+    pub fn java_int() -> Self {
+        LengthPrefixConfig::default()
+    }
+
+    /// Netty LengthFieldBasedFrameDecoder / LengthFieldPrepender（大端，无符号），参数顺序与 Netty 一致
+    /// length_field_length 只支持 1、2、3、4、8
+    pub fn netty(max_frame_length: usize, length_field_offset: usize, length_field_length: usize, length_adjustment: isize, initial_bytes_to_strip: usize) -> io::Result<Self> {
+        let width = match length_field_length {
+            1 => LengthWidth::U8,
+            2 => LengthWidth::U16,
+            3 => LengthWidth::U24,
+            4 => LengthWidth::U32,
+            8 => LengthWidth::U64,
+            _ => return Err(io::Error::new(ErrorKind::InvalidInput, "length_field_length must be 1, 2, 3, 4 or 8")),
+        };
+        Ok(LengthPrefixConfig {
+            width,
+            endian: Endian::Big,
+            signed: false,
+            length_field_offset,
+            length_adjustment,
+            initial_bytes_to_strip: Some(initial_bytes_to_strip),
+            max_frame_length: Some(max_frame_length),
+        })
+    }
+
+    /// 2 个byte小端 u16
+    pub fn u16_le() -> Self {
+        LengthPrefixConfig {
+            width: LengthWidth::U16,
+            endian: Endian::Little,
+            signed: false,
+            ..LengthPrefixConfig::default()
+        }
+    }
+
+    /// 8 个byte大端 u64
+    pub fn u64_be() -> Self {
+        LengthPrefixConfig {
+            width: LengthWidth::U64,
+            signed: false,
+            ..LengthPrefixConfig::default()
+        }
+    }
+
+    /// LEB128 varint，与 protobuf writeDelimitedTo 的长度前缀一致
+    pub fn varint() -> Self {
+        LengthPrefixConfig {
+            width: LengthWidth::Varint,
+            signed: false,
+            ..LengthPrefixConfig::default()
+        }
+    }
+
+    /// 解析头部至少需要的byte数
+    pub fn min_header_len(&self) -> usize {
+        self.length_field_offset + self.width.size().unwrap_or(1)
+    }
+
+    /// content-length 字段允许的最大值
+    fn max_length_value(&self) -> u64 {
+        let bits = match self.width.size() {
+            Some(size) => size as u32 * 8,
+            None => 64,
+        };
+        let bits = if self.signed { bits - 1 } else { bits };
+        if bits >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << bits) - 1
+        }
+    }
+
+    /// 写入内容长度为 content_len 的帧头部
+    pub fn encode_header<B: BufMut>(&self, content_len: usize, dst: &mut B) -> io::Result<()> {
+        let value = content_len as i128 - self.length_adjustment as i128;
+        if value < 0 || value > self.max_length_value() as i128 {
+            return Err(Error::InvalidLength("content-length out of range").into());
+        }
+        let value = value as u64;
+
+        dst.put_bytes(0, self.length_field_offset);
+        match self.width.size() {
+            Some(size) => {
+                let bytes = match self.endian {
+                    Endian::Big => value.to_be_bytes()[8 - size..].to_vec(),
+                    Endian::Little => value.to_le_bytes()[..size].to_vec(),
+                };
+                dst.put_slice(&bytes);
+            }
+            None => {
+                let mut value = value;
+                while value >= 0x80 {
+                    dst.put_u8((value as u8 & 0x7f) | 0x80);
+                    value >>= 7;
+                }
+                dst.put_u8(value as u8);
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置整个帧允许的最大byte数
+    pub fn max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = Some(max_frame_length);
+        self
+    }
+
+    /// 解析帧头部
+    /// 头部未接收完整返回 None，否则返回（头部byte数，整个帧的byte数）
+    /// 帧超出 max_frame_length 时返回 FrameTooLarge，此时只有头部被消费，剩余 remaining 个byte仍在连接中
+    pub fn decode_header(&self, src: &[u8]) -> io::Result<Option<(usize, usize)>> {
+        let offset = self.length_field_offset;
+        if src.len() < self.min_header_len() {
+            return Ok(None);
+        }
+        let field = &src[offset..];
+        let (field_len, value) = match self.width.size() {
+            Some(size) => {
+                let field = &field[..size];
+                let value = match self.endian {
+                    Endian::Big => field.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64),
+                    Endian::Little => field.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64),
+                };
+                (size, value)
+            }
+            None => {
+                let mut value = 0u64;
+                let mut field_len = None;
+                for (i, b) in field.iter().take(VARINT_MAX_SIZE).enumerate() {
+                    let bits = (*b & 0x7f) as u64;
+                    if i == VARINT_MAX_SIZE - 1 && bits > 1 {
+                        return Err(Error::InvalidLength("varint content-length overflow").into());
+                    }
+                    value |= bits << (7 * i);
+                    if b & 0x80 == 0 {
+                        field_len = Some(i + 1);
+                        break;
+                    }
+                }
+                match field_len {
+                    Some(field_len) => (field_len, value),
+                    None if field.len() >= VARINT_MAX_SIZE => {
+                        return Err(Error::InvalidLength("varint content-length overflow").into());
+                    }
+                    None => return Ok(None),
+                }
+            }
+        };
+        if value > self.max_length_value() {
+            return Err(Error::InvalidLength("content-length out of range").into());
+        }
+
+        let header_len = offset + field_len;
+        let frame_len = header_len as i128 + value as i128 + self.length_adjustment as i128;
+        if frame_len < header_len as i128 || frame_len > usize::MAX as i128 {
+            return Err(Error::InvalidLength("content-length out of range").into());
+        }
+        let frame_len = frame_len as usize;
+        if let Some(max) = self.max_frame_length {
+            if frame_len > max {
+                return Err(FrameTooLarge::new(frame_len, max, Some(frame_len - header_len)).into());
+            }
+        }
+        if self.strip_len(header_len) > frame_len {
+            return Err(Error::InvalidLength("initial_bytes_to_strip larger than frame").into());
+        }
+        Ok(Some((header_len, frame_len)))
+    }
+
+    /// 接收时从帧开头去掉的byte数
+    pub fn strip_len(&self, header_len: usize) -> usize {
+        self.initial_bytes_to_strip.unwrap_or(header_len)
+    }
+}
+
+/// 结束标识（分隔符）格式
+/// 精确匹配任意byte序列，例如 \n\n、\r\n\r\n、\n、\0
+///
+/// 设置 escape 后消息内容中可以包含结束标识：
+/// 发送时在转义字符本身以及结束标识的第一个byte前插入转义字符，接收时去掉转义字符，
+/// 因此只有未被转义的结束标识才会结束消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelimiterConfig {
+    /// 结束标识，不能为空
+    pub delimiter: Vec<u8>,
+    /// 接收时是否保留结束标识
+    pub keep_delimiter: bool,
+    /// 单条消息（不含结束标识）允许的最大byte数，None 表示不限制
+    /// 设置 escape 时按转义后的长度计算
+    pub max_frame_length: Option<usize>,
+    /// 转义字符，None 表示不转义，消息内容中不能包含结束标识
+    pub escape: Option<u8>,
+}
+
+impl Default for DelimiterConfig {
+    /// 空行（\n\n），与 send_line/read_line 的格式一致
+    fn default() -> Self {
+        DelimiterConfig::blank_line()
+    }
+}
+
+impl DelimiterConfig {
+    /// delimiter 为空时 panic
+    pub fn new(delimiter: impl Into<Vec<u8>>) -> Self {
+        let delimiter = delimiter.into();
+        assert!(!delimiter.is_empty(), "delimiter must not be empty");
+        DelimiterConfig {
+            delimiter,
+            keep_delimiter: false,
+            max_frame_length: None,
+            escape: None,
+        }
+    }
+
+    /// 空行 \n\n
+    pub fn blank_line() -> Self {
+        DelimiterConfig::new(&b"\n\n"[..])
+    }
+
+    /// 换行 \n
+    pub fn line() -> Self {
+        DelimiterConfig::new(&b"\n"[..])
+    }
+
+    /// 回车换行 \r\n
+    pub fn crlf() -> Self {
+        DelimiterConfig::new(&b"\r\n"[..])
+    }
+
+    /// HTTP 头部结束标识 \r\n\r\n
+    pub fn http_header() -> Self {
+        DelimiterConfig::new(&b"\r\n\r\n"[..])
+    }
+
+    /// NUL 字符 \0
+    pub fn nul() -> Self {
+        DelimiterConfig::new(&b"\0"[..])
+    }
+
+    /// 接收时是否保留结束标识
+    pub fn keep_delimiter(mut self, keep_delimiter: bool) -> Self {
+        self.keep_delimiter = keep_delimiter;
+        self
+    }
+
+    /// 设置单条消息允许的最大byte数
+    pub fn max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = Some(max_frame_length);
+        self
+    }
+
+    /// 设置转义字符，转义字符不能是结束标识的第一个byte，否则 panic
+    pub fn escape(mut self, escape: u8) -> Self {
+        assert_ne!(escape, self.delimiter[0], "escape must differ from the first byte of the delimiter");
+        self.escape = Some(escape);
+        self
+    }
+
+    /// 发送前转义消息内容，未设置 escape 时原样返回
+    pub fn escape_payload<'a>(&self, msg: &'a [u8]) -> Cow<'a, [u8]> {
+        let escape = match self.escape {
+            Some(escape) => escape,
+            None => return Cow::Borrowed(msg),
+        };
+        let first = self.delimiter[0];
+        if !msg.iter().any(|b| *b == escape || *b == first) {
+            return Cow::Borrowed(msg);
+        }
+        let mut escaped = Vec::with_capacity(msg.len() + msg.len() / 8);
+        for b in msg {
+            if *b == escape || *b == first {
+                escaped.push(escape);
+            }
+            escaped.push(*b);
+        }
+        Cow::Owned(escaped)
+    }
+
+    /// 接收后去掉转义字符，未设置 escape 时原样返回
+    pub fn unescape_payload<'a>(&self, msg: &'a [u8]) -> Cow<'a, [u8]> {
+        let escape = match self.escape {
+            Some(escape) if msg.contains(&escape) => escape,
+            _ => return Cow::Borrowed(msg),
+        };
+        let mut unescaped = Vec::with_capacity(msg.len());
+        let mut bytes = msg.iter();
+        while let Some(b) = bytes.next() {
+            if *b == escape {
+                //转义字符后面的byte按原样保留
+                match bytes.next() {
+                    Some(b) => unescaped.push(*b),
+                    None => break,
+                }
+            } else {
+                unescaped.push(*b);
+            }
+        }
+        Cow::Owned(unescaped)
+    }
+
+    /// 从 start 开始查找未被转义的结束标识
+    /// 找到时返回 Ok(位置)，否则返回 Err(下一次继续查找的位置)
+    pub fn find_delimiter(&self, src: &[u8], start: usize) -> Result<usize, usize> {
+        let delimiter = &self.delimiter[..];
+        let escape = match self.escape {
+            Some(escape) => escape,
+            None => {
+                return match src[start..].windows(delimiter.len()).position(|w| w == delimiter) {
+                    Some(pos) => Ok(start + pos),
+                    //结束标识可能被拆分在两次读取之间，保留最后几个byte重新查找
+                    None => Err(src.len().saturating_sub(delimiter.len() - 1).max(start)),
+                };
+            }
+        };
+        let mut i = start;
+        while i < src.len() {
+            if src[i] == escape {
+                if i + 1 >= src.len() {
+                    //转义字符后面的byte还没有收到
+                    return Err(i);
+                }
+                i += 2;
+                continue;
+            }
+            let rest = &src[i..];
+            if rest.starts_with(delimiter) {
+                return Ok(i);
+            }
+            if rest.len() < delimiter.len() && delimiter.starts_with(rest) {
+                return Err(i);
+            }
+            i += 1;
+        }
+        Err(i)
+    }
+}
+
+/// 消息超出最大长度限制
+/// Trait 方法中以 Error::FrameTooLarge 返回；codec 中以 io::Error（ErrorKind::InvalidData）的形式返回，可以通过 FrameTooLarge::from_io_error 识别
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTooLarge {
+    /// 帧的长度，长度未知（空行、EOF 结束标识）时为已经读取的byte数
+    pub len: usize,
+    /// 允许的最大长度
+    pub max: usize,
+    /// 该帧仍留在连接中未读取的byte数
+    /// Some 表示连接仍然可用，调用方可以跳过这些byte继续读取下一条消息；None 表示无法确定消息边界，应关闭连接
+    pub remaining: Option<usize>,
+}
+
+impl FrameTooLarge {
+    pub fn new(len: usize, max: usize, remaining: Option<usize>) -> Self {
+        FrameTooLarge { len, max, remaining }
+    }
+
+    /// 判断 io::Error 是否为 FrameTooLarge
+    pub fn from_io_error(err: &io::Error) -> Option<&FrameTooLarge> {
+        err.get_ref().and_then(|e| e.downcast_ref::<FrameTooLarge>())
+    }
+}
+
+impl fmt::Display for FrameTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame too large: {} bytes exceeds max {} bytes", self.len, self.max)
+    }
+}
+
+impl error::Error for FrameTooLarge {}
+
+impl From<FrameTooLarge> for io::Error {
+    fn from(err: FrameTooLarge) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
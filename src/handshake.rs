@@ -0,0 +1,300 @@
+//! 连接握手
+//! 建立连接后由客户端先发送自己支持的协议版本、分帧方式、最大帧长度、压缩算法和序列化格式，
+//! 服务端按客户端的优先顺序选出双方都支持的设置并回复，没有共同的设置时回复拒绝原因并返回 Error::Handshake
+//! 握手消息固定使用 send_len 的格式，与协商出的分帧方式无关
+//!
+//! 握手是可选的，通过 Connection::client/Connection::server 建立连接时使用，双方必须同时开启
+
+use bytes::Buf;
+use tokio::io::{AsyncRead, AsyncWrite};
+use crate::codec::LengthPrefixCodec;
+use crate::compress::Compression;
+use crate::error::Error;
+use crate::frame::LengthPrefixConfig;
+use crate::message::Format;
+use crate::reader::MessageReader;
+use crate::socket::SocketAsyncSendTrait;
+use crate::versioned::DEFAULT_MAX_FRAME_LENGTH;
+
+/// 握手消息开头的固定标识
+pub const HANDSHAKE_MAGIC: [u8; 4] = *b"TDHS";
+/// 当前协议版本
+pub const PROTOCOL_VERSION: u8 = 1;
+/// 握手消息的最大长度，防止对端发送超大的握手消息
+const MAX_HANDSHAKE_LEN: usize = 64 * 1024;
+
+const KIND_HELLO: u8 = 0;
+const KIND_ACCEPT: u8 = 1;
+const KIND_REJECT: u8 = 2;
+
+/// 分帧方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// send_len/read_len 的 content-length 格式
+    #[default]
+    Len,
+    /// send_line/read_line 的空行结束格式，只适合文本消息，协商结果中不使用压缩
+    Line,
+    /// versioned 模块的版本化帧
+    Versioned,
+}
+
+impl Framing {
+    pub fn id(self) -> u8 {
+        match self {
+            Framing::Len => 0,
+            Framing::Line => 1,
+            Framing::Versioned => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Framing> {
+        match id {
+            0 => Some(Framing::Len),
+            1 => Some(Framing::Line),
+            2 => Some(Framing::Versioned),
+            _ => None,
+        }
+    }
+}
+
+/// 本端支持的设置，列表按优先顺序排列，协商时以客户端的顺序为准
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handshake {
+    pub min_version: u8,
+    pub max_version: u8,
+    pub framings: Vec<Framing>,
+    /// 本端能接收的最大帧长度，协商结果取双方的较小值
+    pub max_frame_length: usize,
+    pub compressions: Vec<Compression>,
+    pub formats: Vec<Format>,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Handshake {
+            min_version: PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            framings: vec![Framing::Len],
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            compressions: vec![Compression::None],
+            formats: vec![Format::Json],
+        }
+    }
+}
+
+/// 协商出的设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub version: u8,
+    pub framing: Framing,
+    pub max_frame_length: usize,
+    pub compression: Compression,
+    pub format: Format,
+}
+
+impl Handshake {
+    pub fn versions(mut self, min: u8, max: u8) -> Self {
+        self.min_version = min;
+        self.max_version = max;
+        self
+    }
+
+    pub fn framings(mut self, framings: Vec<Framing>) -> Self {
+        self.framings = framings;
+        self
+    }
+
+    pub fn max_frame_length(mut self, max: usize) -> Self {
+        self.max_frame_length = max;
+        self
+    }
+
+    pub fn compressions(mut self, compressions: Vec<Compression>) -> Self {
+        self.compressions = compressions;
+        self
+    }
+
+    pub fn formats(mut self, formats: Vec<Format>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// 服务端按客户端的优先顺序选出双方都支持的设置，失败时返回原因
+    pub fn negotiate(&self, client: &Handshake) -> Result<Settings, &'static str> {
+        let version = self.max_version.min(client.max_version);
+        if version < self.min_version.max(client.min_version) {
+            return Err("no common protocol version");
+        }
+        let framing = first_common(&client.framings, &self.framings).ok_or("no common framing")?;
+        let compression = if framing == Framing::Line {
+            Compression::None
+        } else {
+            first_common(&client.compressions, &self.compressions).ok_or("no common compression")?
+        };
+        let format = first_common(&client.formats, &self.formats).ok_or("no common format")?;
+        Ok(Settings {
+            version,
+            framing,
+            max_frame_length: self.max_frame_length.min(client.max_frame_length),
+            compression,
+            format,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut dst = HANDSHAKE_MAGIC.to_vec();
+        dst.extend_from_slice(&[KIND_HELLO, self.min_version, self.max_version]);
+        dst.extend_from_slice(&(self.max_frame_length as u64).to_be_bytes());
+        encode_ids(&mut dst, self.framings.iter().map(|f| f.id()));
+        encode_ids(&mut dst, self.compressions.iter().map(|c| c.id()));
+        encode_ids(&mut dst, self.formats.iter().map(|f| f.id()));
+        dst
+    }
+
+    /// 解析客户端的握手消息，本端不认识的标识直接忽略
+    fn decode(mut src: &[u8]) -> Result<Handshake, Error> {
+        if take(&mut src, 1)?[0] != KIND_HELLO {
+            return Err(malformed());
+        }
+        let versions = take(&mut src, 2)?;
+        Ok(Handshake {
+            min_version: versions[0],
+            max_version: versions[1],
+            max_frame_length: take_len(&mut src)?,
+            framings: decode_ids(&mut src, Framing::from_id)?,
+            compressions: decode_ids(&mut src, Compression::from_id)?,
+            formats: decode_ids(&mut src, Format::from_id)?,
+        })
+    }
+}
+
+impl Settings {
+    fn encode(&self) -> Vec<u8> {
+        let mut dst = HANDSHAKE_MAGIC.to_vec();
+        dst.extend_from_slice(&[KIND_ACCEPT, self.version, self.framing.id()]);
+        dst.extend_from_slice(&(self.max_frame_length as u64).to_be_bytes());
+        dst.extend_from_slice(&[self.compression.id(), self.format.id()]);
+        dst
+    }
+
+    fn decode(mut src: &[u8]) -> Result<Settings, Error> {
+        let version = take(&mut src, 1)?[0];
+        let framing = take(&mut src, 1)?[0];
+        let max_frame_length = take_len(&mut src)?;
+        let ids = take(&mut src, 2)?;
+        //本端无法识别的标识一定不在本端发送的列表中
+        Ok(Settings {
+            version,
+            framing: Framing::from_id(framing).ok_or_else(unsupported)?,
+            max_frame_length,
+            compression: Compression::from_id(ids[0]).ok_or_else(unsupported)?,
+            format: Format::from_id(ids[1]).ok_or_else(unsupported)?,
+        })
+    }
+
+    /// 检查服务端选择的设置是否都在客户端发送的范围内
+    fn offered_by(&self, handshake: &Handshake) -> bool {
+        //Line 分帧时 negotiate 固定不使用压缩
+        let compression_offered = handshake.compressions.contains(&self.compression)
+            || (self.framing == Framing::Line && self.compression == Compression::None);
+        (handshake.min_version..=handshake.max_version).contains(&self.version)
+            && handshake.framings.contains(&self.framing)
+            && compression_offered
+            && handshake.formats.contains(&self.format)
+            && self.max_frame_length <= handshake.max_frame_length
+    }
+}
+
+/// 客户端握手：发送本端支持的设置，等待服务端回复
+/// 服务端选择了本端没有提供的设置时返回 Error::Handshake
+pub async fn client<S>(reader: &mut MessageReader<S>, handshake: &Handshake) -> Result<Settings, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    reader.send_len_bytes(&handshake.encode()).await?;
+    let reply = read_message(reader).await?;
+    let mut src = reply.as_slice();
+    match take(&mut src, 1)?[0] {
+        KIND_ACCEPT => {
+            let settings = Settings::decode(src)?;
+            if !settings.offered_by(handshake) {
+                return Err(unsupported());
+            }
+            Ok(settings)
+        }
+        KIND_REJECT => Err(Error::Handshake(format!("rejected by peer: {}", String::from_utf8_lossy(src)))),
+        _ => Err(malformed()),
+    }
+}
+
+/// 服务端握手：读取客户端支持的设置，回复协商结果
+pub async fn server<S>(reader: &mut MessageReader<S>, handshake: &Handshake) -> Result<Settings, Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let hello = read_message(reader).await?;
+    let client = Handshake::decode(&hello)?;
+    match handshake.negotiate(&client) {
+        Ok(settings) => {
+            reader.send_len_bytes(&settings.encode()).await?;
+            Ok(settings)
+        }
+        Err(reason) => {
+            let mut reply = HANDSHAKE_MAGIC.to_vec();
+            reply.push(KIND_REJECT);
+            reply.extend_from_slice(reason.as_bytes());
+            reader.send_len_bytes(&reply).await?;
+            Err(Error::Handshake(reason.to_string()))
+        }
+    }
+}
+
+/// 读取一条握手消息，返回 magic 之后的内容
+async fn read_message<S>(reader: &mut MessageReader<S>) -> Result<Vec<u8>, Error>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut codec = LengthPrefixCodec::with_config(LengthPrefixConfig::default().max_frame_length(MAX_HANDSHAKE_LEN));
+    let msg = reader.read_frame(&mut codec).await?.ok_or(Error::PeerClosed)?;
+    match msg.strip_prefix(&HANDSHAKE_MAGIC) {
+        Some(msg) => Ok(msg.to_vec()),
+        None => Err(Error::Handshake("peer did not send a handshake".to_string())),
+    }
+}
+
+fn first_common<T: PartialEq + Copy>(preferred: &[T], supported: &[T]) -> Option<T> {
+    preferred.iter().copied().find(|item| supported.contains(item))
+}
+
+fn encode_ids(dst: &mut Vec<u8>, ids: impl ExactSizeIterator<Item = u8>) {
+    dst.push(ids.len().min(u8::MAX as usize) as u8);
+    dst.extend(ids.take(u8::MAX as usize));
+}
+
+fn decode_ids<T>(src: &mut &[u8], from_id: fn(u8) -> Option<T>) -> Result<Vec<T>, Error> {
+    let count = take(src, 1)?[0] as usize;
+    Ok(take(src, count)?.iter().filter_map(|&id| from_id(id)).collect())
+}
+
+fn take<'a>(src: &mut &'a [u8], n: usize) -> Result<&'a [u8], Error> {
+    if src.len() < n {
+        return Err(malformed());
+    }
+    let (head, rest) = src.split_at(n);
+    *src = rest;
+    Ok(head)
+}
+
+fn take_len(src: &mut &[u8]) -> Result<usize, Error> {
+    let mut len = take(src, 8)?;
+    Ok(usize::try_from(len.get_u64()).unwrap_or(usize::MAX))
+}
+
+fn malformed() -> Error {
+    Error::Handshake("malformed handshake message".to_string())
+}
+
+fn unsupported() -> Error {
+    Error::Handshake("peer chose unsupported settings".to_string())
+}
@@ -1,3 +1,24 @@
 pub mod socket;
+#[cfg(any(feature = "crc32", feature = "xxhash"))]
+pub mod checksum;
+pub mod chunked;
+pub mod codec;
+pub mod compress;
+#[cfg(feature = "serde")]
+pub mod connection;
+pub mod detect;
+pub mod error;
+pub mod frame;
+#[cfg(feature = "serde")]
+pub mod handshake;
+#[cfg(feature = "serde")]
+pub mod message;
+pub mod mux;
+pub mod reader;
+pub mod text;
+pub mod timeout;
+pub mod versioned;
 #[cfg(feature = "vsock")]
-pub mod vsock;
\ No newline at end of file
+pub mod vsock;
+
+pub use error::Error;
@@ -0,0 +1,116 @@
+//! 结构化消息
+//! 开启 serde feature 后可以通过 send_msg/recv_msg 直接收发实现了 Serialize/Deserialize 的类型，
+//! 消息序列化后使用 send_len 的 content-length 格式发送，默认使用 JSON
+//! 开启 bincode、msgpack、cbor、postcard feature 后可以通过 Format 选择更紧凑的二进制格式
+
+use std::error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::error::Error;
+
+/// 消息的序列化格式，收发双方必须使用相同的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Json,
+    #[cfg(feature = "bincode")]
+    Bincode,
+    /// MessagePack（rmp-serde），结构体按字段名编码，字段增减时兼容性更好
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// 最紧凑的格式，适合高频遥测等小消息
+    #[cfg(feature = "postcard")]
+    Postcard,
+}
+
+impl Format {
+    /// 握手时使用的格式标识
+    pub fn id(self) -> u8 {
+        match self {
+            Format::Json => 0,
+            #[cfg(feature = "bincode")]
+            Format::Bincode => 1,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => 2,
+            #[cfg(feature = "cbor")]
+            Format::Cbor => 3,
+            #[cfg(feature = "postcard")]
+            Format::Postcard => 4,
+        }
+    }
+
+    /// 根据标识查找格式，未知或者本端未开启的格式返回 None
+    pub fn from_id(id: u8) -> Option<Format> {
+        match id {
+            0 => Some(Format::Json),
+            #[cfg(feature = "bincode")]
+            1 => Some(Format::Bincode),
+            #[cfg(feature = "msgpack")]
+            2 => Some(Format::MessagePack),
+            #[cfg(feature = "cbor")]
+            3 => Some(Format::Cbor),
+            #[cfg(feature = "postcard")]
+            4 => Some(Format::Postcard),
+            _ => None,
+        }
+    }
+
+    /// 把消息序列化为当前格式
+    pub fn encode<T>(self, msg: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let result: Result<Vec<u8>, Box<dyn error::Error + Send + Sync>> = match self {
+            Format::Json => serde_json::to_vec(msg).map_err(|e| e.into()),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::serialize(msg).map_err(|e| e.into()),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::to_vec_named(msg).map_err(|e| e.into()),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                let mut bytes = vec![];
+                ciborium::into_writer(msg, &mut bytes).map(|_| bytes).map_err(|e| e.into())
+            }
+            #[cfg(feature = "postcard")]
+            Format::Postcard => postcard::to_allocvec(msg).map_err(|e| e.into()),
+        };
+        result.map_err(Error::Serialize)
+    }
+
+    /// 把收到的消息按当前格式反序列化为 T，失败时返回 Error::Deserialize
+    pub fn decode<T>(self, msg: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let result: Result<T, Box<dyn error::Error + Send + Sync>> = match self {
+            Format::Json => serde_json::from_slice(msg).map_err(|e| e.into()),
+            #[cfg(feature = "bincode")]
+            Format::Bincode => bincode::deserialize(msg).map_err(|e| e.into()),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::from_slice(msg).map_err(|e| e.into()),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::from_reader(msg).map_err(|e| e.into()),
+            #[cfg(feature = "postcard")]
+            Format::Postcard => postcard::from_bytes(msg).map_err(|e| e.into()),
+        };
+        result.map_err(Error::Deserialize)
+    }
+}
+
+/// 把消息序列化为 JSON
+pub fn encode_msg<T>(msg: &T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    Format::Json.encode(msg)
+}
+
+/// 把收到的 JSON 反序列化为 T，失败时返回 Error::Deserialize
+pub fn decode_msg<T>(msg: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    Format::Json.decode(msg)
+}
@@ -0,0 +1,190 @@
+//! 多路复用客户端
+//! 同一个 TcpStream/VsockStream 上可以由多个任务同时发送请求，每个请求带一个 id（send_tagged 的格式），
+//! 服务端按收到的 id 回复即可，回复的顺序不需要和请求一致
+//!
+//! MuxClient 内部启动两个任务：写任务按顺序发送请求，读任务按 id 把回复交给等待的请求
+//! 请求超时或者被取消后等待表中的记录会被立即删除，之后到达的回复直接丢弃
+//!
+//! 服务端使用 read_tagged 读取请求，处理完成后使用 send_tagged 以相同的 id 回复
+
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use crate::error::Error;
+use crate::reader::MessageReader;
+use crate::socket::SocketAsyncSendTrait;
+use crate::versioned::DEFAULT_MAX_FRAME_LENGTH;
+
+/// 等待发送的请求数，超出时 request 等待写任务
+const CHANNEL_SIZE: usize = 64;
+
+type Reply = oneshot::Sender<Result<Vec<u8>, Error>>;
+
+struct Shared {
+    next_id: AtomicU64,
+    /// 等待回复的请求，None 表示连接已关闭
+    pending: Mutex<Option<HashMap<u64, Reply>>>,
+}
+
+impl Shared {
+    /// 连接已关闭，通知所有等待中的请求
+    fn close(&self, err: &Error) {
+        let pending = self.pending.lock().unwrap().take();
+        for (_, reply) in pending.into_iter().flatten() {
+            let _ = reply.send(Err(copy_error(err)));
+        }
+    }
+}
+
+/// 最后一个 MuxClient 被释放时停止读任务
+struct ReadTask(JoinHandle<()>);
+
+impl Drop for ReadTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// 请求结束（收到回复、超时、被取消）时从等待表中删除
+struct PendingGuard<'a> {
+    shared: &'a Shared,
+    id: u64,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.shared.pending.lock().unwrap().as_mut() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+/// 多路复用客户端，clone 后可以在多个任务中同时使用同一个连接
+/// 必须在 tokio 运行时中创建，所有 clone 都被释放后关闭连接的写通道并停止读任务
+#[derive(Clone)]
+pub struct MuxClient {
+    shared: Arc<Shared>,
+    sender: mpsc::Sender<(u64, Vec<u8>)>,
+    _read_task: Arc<ReadTask>,
+    timeout: Option<Duration>,
+}
+
+impl MuxClient {
+    /// 单条回复的最大长度为 versioned::DEFAULT_MAX_FRAME_LENGTH
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        MuxClient::with_max_frame_length(stream, DEFAULT_MAX_FRAME_LENGTH)
+    }
+
+    pub fn with_max_frame_length<S>(stream: S, max: usize) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (rd, wr) = tokio::io::split(stream);
+        let mut reader = MessageReader::new(rd);
+        reader.set_max_frame_length(max);
+        let shared = Arc::new(Shared {
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(Some(HashMap::new())),
+        });
+        let (sender, receiver) = mpsc::channel(CHANNEL_SIZE);
+        tokio::spawn(write_loop(wr, receiver, shared.clone()));
+        let read_task = tokio::spawn(read_loop(reader, shared.clone()));
+        MuxClient {
+            shared,
+            sender,
+            _read_task: Arc::new(ReadTask(read_task)),
+            timeout: None,
+        }
+    }
+
+    /// 设置 request 的默认超时时间，只对当前的 MuxClient 及之后的 clone 生效
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// 等待回复的请求数
+    pub fn pending(&self) -> usize {
+        self.shared.pending.lock().unwrap().as_ref().map_or(0, HashMap::len)
+    }
+
+    /// 发送请求并等待回复，设置了默认超时时间时超时返回 Error::Timeout
+    /// 连接在收到回复之前关闭时返回 Error::PeerClosed（或者导致连接关闭的错误）
+    pub async fn request(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.timeout {
+            Some(timeout) => self.request_timeout(msg, timeout).await,
+            None => self.call(msg).await,
+        }
+    }
+
+    /// request 的指定超时时间版本
+    pub async fn request_timeout(&self, msg: &[u8], timeout: Duration) -> Result<Vec<u8>, Error> {
+        tokio::time::timeout(timeout, self.call(msg)).await.map_err(|_| Error::Timeout)?
+    }
+
+    async fn call(&self, msg: &[u8]) -> Result<Vec<u8>, Error> {
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let (reply, receiver) = oneshot::channel();
+        match self.shared.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(id, reply),
+            None => return Err(Error::PeerClosed),
+        };
+        let _guard = PendingGuard { shared: &self.shared, id };
+        //写任务负责发送，请求被取消时不会写出半条消息
+        self.sender.send((id, msg.to_vec())).await.map_err(|_| Error::PeerClosed)?;
+        receiver.await.unwrap_or(Err(Error::PeerClosed))
+    }
+}
+
+async fn write_loop<W>(mut writer: W, mut receiver: mpsc::Receiver<(u64, Vec<u8>)>, shared: Arc<Shared>)
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    while let Some((id, msg)) = receiver.recv().await {
+        if let Err(e) = writer.send_tagged(id, &msg).await {
+            shared.close(&e);
+            return;
+        }
+    }
+    //所有 MuxClient 都已释放，关闭写通道
+    let _ = writer.shutdown().await;
+}
+
+async fn read_loop<R>(mut reader: MessageReader<R>, shared: Arc<Shared>)
+where
+    R: AsyncRead + Unpin,
+{
+    let err = loop {
+        match reader.read_tagged().await {
+            Ok((id, msg)) => {
+                let reply = shared.pending.lock().unwrap().as_mut().and_then(|pending| pending.remove(&id));
+                //没有找到说明请求已经超时或被取消，直接丢弃回复
+                if let Some(reply) = reply {
+                    let _ = reply.send(Ok(msg));
+                }
+            }
+            Err(e) => break e,
+        }
+    };
+    shared.close(&err);
+}
+
+/// Error 不能 clone，通知多个请求时复制错误类型和信息
+fn copy_error(err: &Error) -> Error {
+    match err {
+        Error::PeerClosed => Error::PeerClosed,
+        Error::Timeout => Error::Timeout,
+        err => Error::Io(io::Error::new(err.kind(), err.to_string())),
+    }
+}
@@ -0,0 +1,456 @@
+//! 带持久缓冲区的消息读取器
+//! SocketAsyncRecvTrait::read_line 每次调用都会创建新的 BufReader，多读到的下一条消息的数据会随之丢失，
+//! MessageReader 在多次调用之间保留缓冲区，适合同一个连接上连续发送多条消息（keep-alive）的场景
+//!
+//! MessageReader 的接收方法（包括 *_timeout / *_deadline 版本）都是取消安全（cancel safe）的：
+//! 已经读到的数据只保存在 MessageReader 的缓冲区中，在 tokio::select! 或 tokio::time::timeout 中被取消后，
+//! 再次调用会从上次的位置继续，不会丢失数据也不会错位。以下两种情况需要调用方保存状态：
+//! - read_frame 使用调用方传入的 decoder，取消后需要使用同一个 decoder 重试
+//! - read_chunked 返回的 ChunkedReader 保存了当前块的进度，取消后需要继续使用同一个 ChunkedReader
+//!
+//! MessageReader 不实现 AsyncRead，socket::SocketAsyncRecvTrait 的方法不能用于 MessageReader
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use bytes::{Buf, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+use tokio::time::Instant;
+use tokio_util::codec::Decoder;
+use crate::error::Error;
+#[cfg(any(feature = "crc32", feature = "xxhash"))]
+use crate::checksum::Checksum;
+use crate::chunked::ChunkedReader;
+use crate::codec::{BlankLineCodec, DelimiterCodec, EofCodec, LengthPrefixCodec};
+use crate::compress::CompressionConfig;
+use crate::frame::{DelimiterConfig, FrameTooLarge, LengthPrefixConfig};
+use crate::socket::{split_tag, BUFFER_SIZE};
+use crate::text::Utf8Mode;
+use crate::timeout::{recv_timeout_methods, with_deadline};
+use crate::versioned::{Frame, FrameOptions, DEFAULT_MAX_FRAME_LENGTH};
+#[cfg(feature = "encoding")]
+use crate::text::{decode_text, Encoding};
+#[cfg(feature = "serde")]
+use crate::message::Format;
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "prost")]
+use prost::Message;
+
+/// 持有读缓冲区的消息读取器
+/// 方法与 SocketAsyncRecvTrait 一一对应，多读到的数据保留在缓冲区中供下一次调用使用
+/// 同时透传 AsyncWrite，可以直接使用 SocketAsyncSendTrait 发送消息
+/// 不实现 AsyncRead：SocketAsyncRecvTrait 的方法不经过缓冲区，会丢失多读到的数据，接收只能使用这里的方法
+/// 接收方法都是取消安全的，可以放在 tokio::select! 的分支中反复调用（read_frame、read_chunked 的例外见模块说明）
+#[derive(Debug)]
+pub struct MessageReader<R> {
+    inner: R,
+    buf: BytesMut,
+    length: LengthPrefixCodec,
+    line: BlankLineCodec,
+    eof: EofCodec,
+    /// read_len_with 等使用临时解码器的方法遇到超长帧时，下一次读取之前还需要跳过的byte数
+    discarding: usize,
+    utf8_mode: Utf8Mode,
+}
+
+impl<R> MessageReader<R> {
+    /// 单条消息默认最多 versioned::DEFAULT_MAX_FRAME_LENGTH 个byte，可通过 set_max_frame_length 修改
+    pub fn new(inner: R) -> Self {
+        let max = DEFAULT_MAX_FRAME_LENGTH;
+        MessageReader {
+            inner,
+            buf: BytesMut::with_capacity(BUFFER_SIZE),
+            length: LengthPrefixCodec::with_config(LengthPrefixConfig::default().max_frame_length(max)),
+            line: BlankLineCodec::with_max_length(max),
+            eof: EofCodec::with_max_length(max),
+            discarding: 0,
+            utf8_mode: Utf8Mode::default(),
+        }
+    }
+
+    /// 使用指定的 content-length 头部格式，config 没有设置 max_frame_length 时使用默认的最大长度
+    pub fn with_length_config(inner: R, mut config: LengthPrefixConfig) -> Self {
+        let mut reader = MessageReader::new(inner);
+        config.max_frame_length = config.max_frame_length.or(Some(DEFAULT_MAX_FRAME_LENGTH));
+        reader.length = LengthPrefixCodec::with_config(config);
+        reader
+    }
+
+    /// 设置该连接上单条消息允许的最大byte数，对所有读取方式生效
+    pub fn set_max_frame_length(&mut self, max: usize) {
+        self.length = LengthPrefixCodec::with_config(self.length.config().max_frame_length(max));
+        self.line = BlankLineCodec::with_max_length(max);
+        self.eof = EofCodec::with_max_length(max);
+    }
+
+    /// 设置 recv/read_len/read_line 转换 String 的方式，默认 Utf8Mode::Strict
+    pub fn set_utf8_mode(&mut self, mode: Utf8Mode) {
+        self.utf8_mode = mode;
+    }
+
+    pub fn utf8_mode(&self) -> Utf8Mode {
+        self.utf8_mode
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// 已读取但还未被消费的数据
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// 丢弃缓冲区开头的 n 个byte，例如已经识别过的前缀
+    pub fn consume(&mut self, n: usize) {
+        self.buf.advance(n.min(self.buf.len()));
+    }
+
+    /// 返回底层连接，缓冲区中未消费的数据会被丢弃
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> MessageReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// 使用任意解码器读取一条消息
+    /// 对端关闭连接且没有剩余数据时返回 None
+    /// 取消后需要使用同一个 decoder 重试，decoder 自身的状态（例如正在跳过的超长帧）由调用方保存
+    pub async fn read_frame<D>(&mut self, decoder: &mut D) -> Result<Option<Bytes>, Error>
+    where
+        D: Decoder<Item = Bytes, Error = io::Error>,
+    {
+        Ok(read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, decoder).await?)
+    }
+
+    /// 从连接中读取一次数据追加到缓冲区，返回读取的byte数，0 表示对端已关闭写通道
+    /// 配合 buffer() 可以在不消费数据的情况下查看后续内容，取消安全
+    pub async fn fill_buffer(&mut self) -> Result<usize, Error> {
+        self.buf.reserve(BUFFER_SIZE);
+        Ok(self.inner.read_buf(&mut self.buf).await?)
+    }
+
+    /// 阻塞等待写通道关闭（read 返回 0）
+    pub async fn recv(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        self.utf8_mode.decode(msg)
+    }
+
+    /// 直接根据头部提供的content-length 来读取消息内容
+    pub async fn read_len(&mut self) -> Result<String, Error> {
+        let msg = self.read_len_bytes().await?;
+        self.utf8_mode.decode(msg)
+    }
+
+    /// 直接根据空行（/n/n）来作为结束标识符
+    pub async fn read_line(&mut self) -> Result<String, Error> {
+        let msg = self.read_line_bytes().await?;
+        self.utf8_mode.decode(msg)
+    }
+
+    /// recv 的宽松版本，不受 utf8_mode 影响，非法 UTF-8 替换成 U+FFFD
+    pub async fn recv_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    /// read_len 的宽松版本，不受 utf8_mode 影响，非法 UTF-8 替换成 U+FFFD
+    pub async fn read_len_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.read_len_bytes().await?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    /// read_line 的宽松版本，不受 utf8_mode 影响，非法 UTF-8 替换成 U+FFFD
+    pub async fn read_line_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.read_line_bytes().await?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    /// recv 的转码版本，按 encoding 解码，同样受 utf8_mode 影响
+    #[cfg(feature = "encoding")]
+    pub async fn recv_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        decode_text(msg, encoding, self.utf8_mode)
+    }
+
+    /// read_len 的转码版本，按 encoding 解码，同样受 utf8_mode 影响
+    #[cfg(feature = "encoding")]
+    pub async fn read_len_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.read_len_bytes().await?;
+        decode_text(msg, encoding, self.utf8_mode)
+    }
+
+    /// read_line 的转码版本，按 encoding 解码，同样受 utf8_mode 影响
+    #[cfg(feature = "encoding")]
+    pub async fn read_line_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.read_line_bytes().await?;
+        decode_text(msg, encoding, self.utf8_mode)
+    }
+
+    /// 按 read_len 的格式读取一条消息并从 JSON 反序列化，失败时返回 Error::Deserialize
+    #[cfg(feature = "serde")]
+    pub async fn recv_msg<M>(&mut self) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        self.recv_msg_with(Format::Json).await
+    }
+
+    /// recv_msg 的指定格式版本
+    #[cfg(feature = "serde")]
+    pub async fn recv_msg_with<M>(&mut self, format: Format) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        let msg = self.read_len_bytes().await?;
+        format.decode(&msg)
+    }
+
+    /// 读取 varint 头部的 protobuf 消息，与 Java/Go 的 parseDelimitedFrom 兼容，解码失败时返回 Error::Deserialize
+    #[cfg(feature = "prost")]
+    pub async fn recv_proto<M>(&mut self) -> Result<M, Error>
+    where
+        M: Message + Default,
+    {
+        self.recv_proto_with(&LengthPrefixConfig::varint()).await
+    }
+
+    /// recv_proto 的指定头部格式版本
+    #[cfg(feature = "prost")]
+    pub async fn recv_proto_with<M>(&mut self, config: &LengthPrefixConfig) -> Result<M, Error>
+    where
+        M: Message + Default,
+    {
+        let msg = self.read_len_frame(config).await?;
+        M::decode(msg).map_err(|e| Error::Deserialize(Box::new(e)))
+    }
+
+    /// 读取 send_compressed 发送的消息，按帧中的标识自动解压
+    /// 解压后超出 config.max_decompressed_size 时返回 FrameTooLarge，该帧已被完整读取，连接仍然可用
+    pub async fn read_compressed(&mut self, config: &CompressionConfig) -> Result<Vec<u8>, Error> {
+        let frame = self.read_len_bytes().await?;
+        config.decompress(&frame)
+    }
+
+    /// 读取 send_versioned 发送的帧，按帧中的标志位校验、解压
+    /// 主版本不支持时返回 Error::UnsupportedVersion，该帧已被完整读取，连接仍然可用
+    /// 超出 options.max_frame_length 时返回 FrameTooLarge，下一次读取会先跳过该帧剩余的数据
+    pub async fn read_versioned(&mut self, options: &FrameOptions) -> Result<Frame, Error> {
+        let frame = self.read_len_frame(&options.length_config()).await?;
+        options.decode(Vec::from(frame))
+    }
+
+    /// 读取 send_tagged 发送的消息，返回请求 id 和消息内容
+    pub async fn read_tagged(&mut self) -> Result<(u64, Vec<u8>), Error> {
+        let frame = self.read_len_bytes().await?;
+        split_tag(frame)
+    }
+
+    /// 读取 send_len_checked 发送的消息并校验，返回去掉校验值后的消息内容
+    /// 校验值不一致时返回 Error::ChecksumMismatch，该帧已被完整读取，连接仍然可用
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    pub async fn read_len_checked(&mut self, checksum: Checksum) -> Result<Vec<u8>, Error> {
+        let frame = self.read_len_bytes().await?;
+        checksum.verify(frame)
+    }
+
+    /// recv 的二进制版本
+    pub async fn recv_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let msg = read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut self.eof).await?;
+        Ok(msg.map(Vec::from).unwrap_or_default())
+    }
+
+    /// read_len 的二进制版本
+    pub async fn read_len_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let msg = read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut self.length).await?;
+        match msg {
+            Some(msg) => Ok(Vec::from(msg)),
+            None => Err(Error::PeerClosed),
+        }
+    }
+
+    /// read_line 的二进制版本（不含结尾的\n\n）
+    pub async fn read_line_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let msg = read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut self.line).await?;
+        Ok(msg.map(Vec::from).unwrap_or_default())
+    }
+
+    /// 按 config 指定的头部格式读取消息内容
+    /// 超出 config.max_frame_length 时返回 FrameTooLarge，该帧剩余的内容在下一次读取时跳过，连接仍然可用
+    /// config 没有设置 max_frame_length 时使用该读取器的最大长度
+    pub async fn read_len_with(&mut self, config: &LengthPrefixConfig) -> Result<Vec<u8>, Error> {
+        Ok(Vec::from(self.read_len_frame(config).await?))
+    }
+
+    /// 使用临时的 LengthPrefixCodec 读取一条消息
+    /// 临时解码器无法保存跳过超长帧的进度，改为记录在 discarding 中
+    async fn read_len_frame(&mut self, config: &LengthPrefixConfig) -> Result<Bytes, Error> {
+        let mut config = *config;
+        config.max_frame_length = config.max_frame_length.or(self.length.config().max_frame_length);
+        let mut codec = LengthPrefixCodec::with_config(config);
+        match read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut codec).await {
+            Ok(Some(frame)) => Ok(frame),
+            Ok(None) => Err(Error::PeerClosed),
+            Err(err) => {
+                if let Some(FrameTooLarge { remaining: Some(remaining), .. }) = FrameTooLarge::from_io_error(&err) {
+                    self.discarding = *remaining;
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    /// recv_bytes 的限制长度版本，超出 max 时返回 FrameTooLarge，此时应关闭连接
+    pub async fn recv_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error> {
+        let mut codec = EofCodec::with_max_length(max);
+        let msg = read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut codec).await?;
+        Ok(msg.map(Vec::from).unwrap_or_default())
+    }
+
+    /// read_line_bytes 的限制长度版本，超出 max 时返回 FrameTooLarge，此时无法确定消息边界，应关闭连接
+    pub async fn read_line_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error> {
+        let mut codec = BlankLineCodec::with_max_length(max);
+        let msg = read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut codec).await?;
+        Ok(msg.map(Vec::from).unwrap_or_default())
+    }
+
+    /// 根据 config 指定的结束标识读取消息
+    pub async fn read_delimited(&mut self, config: &DelimiterConfig) -> Result<Vec<u8>, Error> {
+        let mut codec = DelimiterCodec::new(config.clone());
+        let msg = read_frame(&mut self.inner, &mut self.buf, &mut self.discarding, &mut codec).await?;
+        Ok(msg.map(Vec::from).unwrap_or_default())
+    }
+}
+
+/// 生成 MessageReader 的超时方法，截止时间版本通过 timeout::with_deadline 调用同名的接收方法
+macro_rules! reader_timeout_methods {
+    (
+        $(#[$impl_attr:meta])*
+        impl MessageReader;
+        $(
+            $(#[$attr:meta])*
+            $method:ident => $timeout:ident, $deadline:ident $(<$g:ident>)? ($($arg:ident: $ty:ty),*) -> $ret:ty
+                $(where $wg:ident: $b0:ident $(+ $b:ident)* $(+ ?$unsized:ident)?)?;
+        )*
+    ) => {
+        $(#[$impl_attr])*
+        impl<R> MessageReader<R>
+        where
+            R: AsyncRead + Unpin,
+        {
+            $(
+                #[doc = concat!(stringify!($method), " 的超时版本")]
+                $(#[$attr])*
+                pub async fn $timeout $(<$g>)? (&mut self, $($arg: $ty,)* timeout: Duration) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?
+                {
+                    self.$deadline($($arg,)* Instant::now() + timeout).await
+                }
+
+                #[doc = concat!(stringify!($method), " 的截止时间版本")]
+                $(#[$attr])*
+                pub async fn $deadline $(<$g>)? (&mut self, $($arg: $ty,)* deadline: Instant) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?
+                {
+                    with_deadline(deadline, self.$method($($arg),*)).await
+                }
+            )*
+        }
+    };
+}
+
+recv_timeout_methods!(reader_timeout_methods! {
+    /// 接收方法的超时版本，超时返回 Error::Timeout
+    /// 与 SocketAsyncRecvTimeoutTrait 不同，超时前已经读到的数据保留在缓冲区中，超时后再次调用会从上次的位置继续
+    impl MessageReader;
+});
+
+impl<R> MessageReader<R> {
+    /// 读取 send_chunked 发送的分块数据，缓冲区中已经读到的数据会先被使用
+    /// 返回的 ChunkedReader 读取到结束块后返回 EOF，之后可以继续使用 MessageReader 接收其他消息
+    pub fn read_chunked(&mut self) -> ChunkedReader<BufferedRead<'_, R>> {
+        ChunkedReader::new(BufferedRead { reader: self })
+    }
+}
+
+/// 先跳过上一条超长帧的剩余内容，再从缓冲区中解码一条消息，数据不足时从连接中继续读取
+/// 唯一的 await 点是 read_buf，读到的数据直接追加到 buf 中，跳过的进度记录在 discarding 中，在这里取消不会丢失数据
+async fn read_frame<R, D>(inner: &mut R, buf: &mut BytesMut, discarding: &mut usize, decoder: &mut D) -> Result<Option<Bytes>, io::Error>
+where
+    R: AsyncRead + Unpin,
+    D: Decoder<Item = Bytes, Error = io::Error>,
+{
+    while *discarding > 0 {
+        if buf.is_empty() {
+            buf.reserve(BUFFER_SIZE);
+            if inner.read_buf(buf).await? == 0 {
+                //对端在超长帧结束之前关闭了连接
+                *discarding = 0;
+                return Ok(None);
+            }
+        }
+        let n = (*discarding).min(buf.len());
+        buf.advance(n);
+        *discarding -= n;
+    }
+    loop {
+        if let Some(frame) = decoder.decode(buf)? {
+            return Ok(Some(frame));
+        }
+        buf.reserve(BUFFER_SIZE);
+        let n = inner.read_buf(buf).await?;
+        if n == 0 {
+            //end of Stream
+            return decoder.decode_eof(buf);
+        }
+    }
+}
+
+/// read_chunked 使用的读取端，先返回 MessageReader 缓冲区中未消费的数据，再从连接中读取
+/// ChunkedReader 不会多读结束块之后的数据，读取完成后 MessageReader 可以继续接收后续消息
+#[derive(Debug)]
+pub struct BufferedRead<'a, R> {
+    reader: &'a mut MessageReader<R>,
+}
+
+impl<R> AsyncRead for BufferedRead<'_, R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<Result<(), io::Error>> {
+        let this = &mut *self.get_mut().reader;
+        if this.buf.is_empty() {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+        let n = this.buf.len().min(buf.remaining());
+        buf.put_slice(&this.buf[..n]);
+        this.buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> AsyncWrite for MessageReader<R>
+where
+    R: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
@@ -1,228 +1,513 @@
-use std::{i32, io, mem};
-use std::io::ErrorKind;
-// use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
-use tokio::net::TcpStream;
+use std::{io, mem};
+use std::io::{ErrorKind, IoSlice, Read, Write};
+use bytes::{Buf, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::Decoder;
 use async_trait::async_trait;
-// use tokio::time::error::Elapsed;
-// use tokio::time::timeout;
+#[cfg(any(feature = "crc32", feature = "xxhash"))]
+use crate::checksum::Checksum;
+use crate::chunked::{ChunkedReader, CHUNK_SIZE};
+use crate::codec::DelimiterCodec;
+use crate::compress::CompressionConfig;
+use crate::error::Error;
+use crate::frame::{DelimiterConfig, FrameTooLarge, LengthPrefixConfig};
+use crate::text::Utf8Mode;
+use crate::versioned::{Frame, FrameOptions};
+#[cfg(feature = "encoding")]
+use crate::text::{decode_text, encode_text, Encoding};
+#[cfg(feature = "serde")]
+use crate::message::Format;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "prost")]
+use prost::Message;
 
 pub const CONTENT_LENGTH_SIZE: usize = mem::size_of::<i32>();
 pub const BUFFER_SIZE: usize = 1024;
+/// send_tagged 中请求 id 的byte数
+pub const TAG_SIZE: usize = mem::size_of::<u64>();
 
 
-/// tokio::net::TcpStream Trait实现
+/// tokio 异步流 Trait实现
+/// String 方法只是对 bytes 方法的简单封装，二进制数据（protobuf、图片、加密数据等）请使用 *_bytes 方法
+/// 接收 String 时严格校验 UTF-8，非法数据返回 text::InvalidUtf8 错误，需要容错时显式使用 *_lossy 方法
 #[async_trait]
 pub trait SocketAsyncSendTrait {
     /// 阻塞等待写通道关闭（read 返回 0）
-    async fn send(&mut self, msg: String) -> Result<usize, io::Error>;
+    async fn send(&mut self, msg: String) -> Result<usize, Error>;
     /// 无需等待写通道关闭
     /// read直接根据头部提供的content-length 来读取消息内容
     /// 头部插入content-length 标识
-    async fn send_len(&mut self, msg: String) -> Result<usize, io::Error>;
+    async fn send_len(&mut self, msg: String) -> Result<usize, Error>;
     /// 无需等待写通道关闭
     /// read直接根据空行（/n/n）来作为结束标识符
     /// 尾部插入空行（/n/n）
-    async fn send_line(&mut self, msg: String) -> Result<usize, io::Error>;
+    async fn send_line(&mut self, msg: String) -> Result<usize, Error>;
+    /// send 的二进制版本
+    async fn send_bytes(&mut self, msg: &[u8]) -> Result<usize, Error>;
+    /// send_len 的二进制版本
+    async fn send_len_bytes(&mut self, msg: &[u8]) -> Result<usize, Error>;
+    /// send_line 的二进制版本
+    async fn send_line_bytes(&mut self, msg: &[u8]) -> Result<usize, Error>;
+    /// 尾部插入 config 指定的结束标识，设置了转义字符时先转义消息内容
+    async fn send_delimited(&mut self, msg: &[u8], config: &DelimiterConfig) -> Result<usize, Error>;
+    /// 按 config 指定的头部格式发送 content-length + 消息内容
+    async fn send_len_with(&mut self, msg: &[u8], config: &LengthPrefixConfig) -> Result<usize, Error>;
+    /// send 的转码版本，按 encoding 编码后发送
+    #[cfg(feature = "encoding")]
+    async fn send_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error>;
+    /// send_len 的转码版本，content-length 为编码后的byte数
+    #[cfg(feature = "encoding")]
+    async fn send_len_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error>;
+    /// send_line 的转码版本
+    #[cfg(feature = "encoding")]
+    async fn send_line_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error>;
+    /// 把 msg 序列化为 JSON 后按 send_len 的格式发送
+    #[cfg(feature = "serde")]
+    async fn send_msg<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized;
+    /// send_msg 的指定格式版本
+    #[cfg(feature = "serde")]
+    async fn send_msg_with<M>(&mut self, msg: &M, format: Format) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized;
+    /// 发送 protobuf 消息，使用 varint 头部，与 Java/Go 的 writeDelimitedTo 兼容
+    #[cfg(feature = "prost")]
+    async fn send_proto<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Message;
+    /// send_proto 的指定头部格式版本
+    #[cfg(feature = "prost")]
+    async fn send_proto_with<M>(&mut self, msg: &M, config: &LengthPrefixConfig) -> Result<usize, Error>
+    where
+        M: Message;
+    /// 按 config 压缩后以 send_len 的格式发送，头部之后的第一个byte是压缩算法标识
+    /// 小于 config.threshold 的消息不压缩，返回实际发送的byte数
+    async fn send_compressed(&mut self, msg: &[u8], config: &CompressionConfig) -> Result<usize, Error>;
+    /// 按 versioned 模块的帧格式发送，options 指定是否压缩、附加校验值
+    async fn send_versioned(&mut self, frame: &Frame, options: &FrameOptions) -> Result<usize, Error>;
+    /// 以 send_len 的格式发送，消息内容之前附加 8 个byte（大端）的请求 id，用于 mux 模块匹配请求和回复
+    async fn send_tagged(&mut self, id: u64, msg: &[u8]) -> Result<usize, Error>;
+    /// 以 send_len 的格式发送，消息内容之后附加 checksum 校验值，content-length 包含校验值的长度
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    async fn send_len_checked(&mut self, msg: &[u8], checksum: Checksum) -> Result<usize, Error>;
+    /// 分块发送 src 中的全部数据，适合长度未知或者很大的数据，发送完成后连接仍然可以继续使用
+    /// 每一块都是一个 send_len 格式的帧，最后发送一个长度为 0 的块作为结束标识，返回 src 中读取的byte数
+    async fn send_chunked<R>(&mut self, src: &mut R) -> Result<u64, Error>
+    where
+        R: AsyncRead + Unpin + Send + ?Sized;
 }
 
+/// 接收方法不是取消安全的：在 tokio::select! 中被取消时已经读到的头部或消息内容会丢失，连接无法继续使用
+/// 需要取消后重试时请使用 reader::MessageReader 自身的接收方法，MessageReader 没有实现 AsyncRead，不能调用这里的方法
 #[async_trait]
 pub trait SocketAsyncRecvTrait {
     /// 阻塞等待写通道关闭（read 返回 0）
-    async fn recv(&mut self) -> Result<String, io::Error>;
+    async fn recv(&mut self) -> Result<String, Error>;
     /// 无需等待写通道关闭
     /// 直接根据头部提供的content-length 来读取消息内容
-    async fn read_len(&mut self) -> Result<String, io::Error>;
+    async fn read_len(&mut self) -> Result<String, Error>;
     /// 无需等待写通道关闭
     /// 直接根据空行（/n/n）来作为结束标识符
-    async fn read_line(&mut self) -> Result<String, io::Error>;
+    /// 每次调用都会创建新的缓冲区，多读到的下一条消息会丢失，同一连接连续接收多条消息请使用 reader::MessageReader
+    async fn read_line(&mut self) -> Result<String, Error>;
+    /// recv 的宽松版本，非法 UTF-8 替换成 U+FFFD
+    async fn recv_lossy(&mut self) -> Result<String, Error>;
+    /// read_len 的宽松版本，非法 UTF-8 替换成 U+FFFD
+    async fn read_len_lossy(&mut self) -> Result<String, Error>;
+    /// read_line 的宽松版本，非法 UTF-8 替换成 U+FFFD
+    async fn read_line_lossy(&mut self) -> Result<String, Error>;
+    /// recv 的转码版本，按 encoding 严格解码，非法数据返回 text::InvalidEncoding 错误
+    #[cfg(feature = "encoding")]
+    async fn recv_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error>;
+    /// read_len 的转码版本，按 encoding 严格解码
+    #[cfg(feature = "encoding")]
+    async fn read_len_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error>;
+    /// read_line 的转码版本，按 encoding 严格解码
+    #[cfg(feature = "encoding")]
+    async fn read_line_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error>;
+    /// recv 的二进制版本，原样返回收到的字节
+    async fn recv_bytes(&mut self) -> Result<Vec<u8>, Error>;
+    /// read_len 的二进制版本，原样返回收到的字节
+    async fn read_len_bytes(&mut self) -> Result<Vec<u8>, Error>;
+    /// read_line 的二进制版本，原样返回收到的字节（不含结尾的\n\n），只精确匹配 \n\n
+    async fn read_line_bytes(&mut self) -> Result<Vec<u8>, Error>;
+    /// 按 config 指定的头部格式读取消息内容
+    /// 只读取头部和 content-length 个byte，不会多读后续消息；消息内容不完整时返回 Error::TruncatedFrame
+    /// 超出 config.max_frame_length 时返回 FrameTooLarge，此时只读取了头部，剩余 remaining 个byte仍在连接中，
+    /// 调用方可以跳过这些byte继续读取下一条消息，或者直接关闭连接
+    async fn read_len_with(&mut self, config: &LengthPrefixConfig) -> Result<Vec<u8>, Error>;
+    /// recv_bytes 的限制长度版本，超出 max 时返回 FrameTooLarge
+    /// 此时超出部分所在的一次读取已被丢弃，无法继续接收剩余数据，应关闭连接
+    async fn recv_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error>;
+    /// read_line_bytes 的限制长度版本，超出 max 时返回 FrameTooLarge
+    /// 此时消息已被读取了一部分，无法确定下一条消息的位置，应关闭连接
+    async fn read_line_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error>;
+    /// 根据 config 指定的结束标识读取消息，精确匹配结束标识，设置了转义字符时返回去掉转义后的内容
+    /// 对端关闭连接时剩余数据作为最后一条消息返回
+    async fn read_delimited(&mut self, config: &DelimiterConfig) -> Result<Vec<u8>, Error>;
+    /// 按 read_len 的格式读取一条消息并从 JSON 反序列化，失败时返回 Error::Deserialize
+    #[cfg(feature = "serde")]
+    async fn recv_msg<M>(&mut self) -> Result<M, Error>
+    where
+        M: DeserializeOwned;
+    /// recv_msg 的指定格式版本
+    #[cfg(feature = "serde")]
+    async fn recv_msg_with<M>(&mut self, format: Format) -> Result<M, Error>
+    where
+        M: DeserializeOwned;
+    /// 读取 varint 头部的 protobuf 消息，与 Java/Go 的 parseDelimitedFrom 兼容，解码失败时返回 Error::Deserialize
+    #[cfg(feature = "prost")]
+    async fn recv_proto<M>(&mut self) -> Result<M, Error>
+    where
+        M: Message + Default;
+    /// recv_proto 的指定头部格式版本
+    #[cfg(feature = "prost")]
+    async fn recv_proto_with<M>(&mut self, config: &LengthPrefixConfig) -> Result<M, Error>
+    where
+        M: Message + Default;
+    /// 读取 send_compressed 发送的消息，按帧中的标识自动解压
+    /// 解压后超出 config.max_decompressed_size 时返回 FrameTooLarge，该帧已被完整读取，连接仍然可用
+    async fn read_compressed(&mut self, config: &CompressionConfig) -> Result<Vec<u8>, Error>;
+    /// 读取 send_versioned 发送的帧，按帧中的标志位校验、解压
+    /// 主版本不支持时返回 Error::UnsupportedVersion，该帧已被完整读取，连接仍然可用
+    async fn read_versioned(&mut self, options: &FrameOptions) -> Result<Frame, Error>;
+    /// 读取 send_tagged 发送的消息，返回请求 id 和消息内容
+    async fn read_tagged(&mut self) -> Result<(u64, Vec<u8>), Error>;
+    /// 读取 send_len_checked 发送的消息并校验，返回去掉校验值后的消息内容
+    /// 校验值不一致时返回 Error::ChecksumMismatch，该帧已被完整读取，连接仍然可用
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    async fn read_len_checked(&mut self, checksum: Checksum) -> Result<Vec<u8>, Error>;
+    /// 读取 send_chunked 发送的分块数据，返回的 ChunkedReader 读取到结束块后返回 EOF
+    fn read_chunked(&mut self) -> ChunkedReader<&mut Self>
+    where
+        Self: Sized;
 }
 
+/// 任意 AsyncWrite 的通用实现
+/// TcpStream、WriteHalf、OwnedWriteHalf、UnixStream、VsockStream、DuplexStream 等均可直接使用
 #[async_trait]
-impl SocketAsyncSendTrait for TcpStream {
-    async fn send(&mut self, msg: String) -> Result<usize, io::Error> {
-        let bytes = msg.as_bytes();
-        let len = bytes.len();
-        let mut write_size = 0;
-        while write_size < len {
-            let mut end = write_size + BUFFER_SIZE;
-            if end > len {
-                end = len;
-            }
-            self.write_all(&bytes[write_size..end]).await?;
-            write_size = end;
-        };
-        Ok(write_size)
-    }
-
-    async fn send_len(&mut self, msg: String) -> Result<usize, io::Error> {
-        let mut bytes = vec![];
-        //头部插入4个byte的content-length值
-        let content_len: i32 = msg.as_bytes().len().try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Convert Error usize to i32"))?;
-        let content_len: [u8; CONTENT_LENGTH_SIZE] = content_len.to_be_bytes();
-        bytes.extend_from_slice(&content_len[..]);
-        //插入消息内容
-        bytes.extend_from_slice(msg.as_bytes());
-
-        let mut write_size = 0;
-        let len = bytes.len();
-        while write_size < len {
-            let mut end = write_size + BUFFER_SIZE;
-            if end > len {
-                end = len;
-            }
-            self.write_all(&bytes[write_size..end]).await?;
-            write_size = end;
-        };
-        Ok(write_size)
+impl<T> SocketAsyncSendTrait for T
+where
+    T: AsyncWrite + Unpin + Send,
+{
+    async fn send(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_bytes(msg.as_bytes()).await
     }
 
-    async fn send_line(&mut self, mut msg: String) -> Result<usize, io::Error> {
-        msg.push_str("\n\n");
-        self.send(msg).await
+    async fn send_len(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_len_bytes(msg.as_bytes()).await
     }
-}
 
-#[async_trait]
-impl SocketAsyncRecvTrait for TcpStream {
-    async fn recv(&mut self) -> Result<String, io::Error> {
-        let mut msg = vec![];
-        let mut buf = [0u8; BUFFER_SIZE];
+    async fn send_line(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_line_bytes(msg.as_bytes()).await
+    }
+
+    async fn send_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        //整块交给 write_all，由底层决定每次写多少，避免按 BUFFER_SIZE 切片产生多余的系统调用
+        self.write_all(msg).await?;
+        Ok(msg.len())
+    }
+
+    async fn send_len_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        self.send_len_with(msg, &LengthPrefixConfig::default()).await
+    }
+
+    async fn send_line_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        self.send_delimited(msg, &DelimiterConfig::blank_line()).await
+    }
+
+    async fn send_delimited(&mut self, msg: &[u8], config: &DelimiterConfig) -> Result<usize, Error> {
+        //设置了转义字符时先转义消息内容
+        let msg = config.escape_payload(msg);
+        //消息内容和尾部结束标识一起用 write_vectored 发送
+        Ok(write_all_vectored(self, Buf::chain(msg.as_ref(), config.delimiter.as_slice())).await?)
+    }
+
+    async fn send_len_with(&mut self, msg: &[u8], config: &LengthPrefixConfig) -> Result<usize, Error> {
+        let mut header = Vec::with_capacity(config.min_header_len());
+        //头部只编码content-length值，消息内容不再拷贝
+        config.encode_header(msg.len(), &mut header)?;
+        //头部和消息内容用 write_vectored 一起发送
+        Ok(write_all_vectored(self, Buf::chain(header.as_slice(), msg)).await?)
+    }
+
+    #[cfg(feature = "encoding")]
+    async fn send_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error> {
+        let msg = encode_text(msg, encoding)?;
+        self.send_bytes(&msg).await
+    }
+
+    #[cfg(feature = "encoding")]
+    async fn send_len_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error> {
+        let msg = encode_text(msg, encoding)?;
+        self.send_len_bytes(&msg).await
+    }
+
+    #[cfg(feature = "encoding")]
+    async fn send_line_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error> {
+        let msg = encode_text(msg, encoding)?;
+        self.send_line_bytes(&msg).await
+    }
+
+    #[cfg(feature = "serde")]
+    async fn send_msg<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized,
+    {
+        self.send_msg_with(msg, Format::Json).await
+    }
+
+    #[cfg(feature = "serde")]
+    async fn send_msg_with<M>(&mut self, msg: &M, format: Format) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized,
+    {
+        let msg = format.encode(msg)?;
+        self.send_len_bytes(&msg).await
+    }
+
+    #[cfg(feature = "prost")]
+    async fn send_proto<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Message,
+    {
+        self.send_proto_with(msg, &LengthPrefixConfig::varint()).await
+    }
+
+    #[cfg(feature = "prost")]
+    async fn send_proto_with<M>(&mut self, msg: &M, config: &LengthPrefixConfig) -> Result<usize, Error>
+    where
+        M: Message,
+    {
+        let msg = msg.encode_to_vec();
+        self.send_len_with(&msg, config).await
+    }
+
+    async fn send_compressed(&mut self, msg: &[u8], config: &CompressionConfig) -> Result<usize, Error> {
+        let (compression, payload) = config.compress(msg)?;
+        //content-length 包含压缩算法标识
+        let mut header = Vec::with_capacity(CONTENT_LENGTH_SIZE + 1);
+        LengthPrefixConfig::default().encode_header(payload.len() + 1, &mut header)?;
+        header.push(compression.id());
+        Ok(write_all_vectored(self, Buf::chain(header.as_slice(), payload.as_ref())).await?)
+    }
+
+    async fn send_versioned(&mut self, frame: &Frame, options: &FrameOptions) -> Result<usize, Error> {
+        let frame = options.encode_parts(frame)?;
+        Ok(write_all_vectored(self, Buf::chain(Buf::chain(frame.head.as_slice(), frame.payload.as_ref()), frame.trailer.as_slice())).await?)
+    }
+
+    async fn send_tagged(&mut self, id: u64, msg: &[u8]) -> Result<usize, Error> {
+        let mut header = Vec::with_capacity(CONTENT_LENGTH_SIZE + TAG_SIZE);
+        LengthPrefixConfig::default().encode_header(msg.len() + TAG_SIZE, &mut header)?;
+        header.extend_from_slice(&id.to_be_bytes());
+        Ok(write_all_vectored(self, Buf::chain(header.as_slice(), msg)).await?)
+    }
+
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    async fn send_len_checked(&mut self, msg: &[u8], checksum: Checksum) -> Result<usize, Error> {
+        let trailer = checksum.trailer(msg);
+        let mut header = Vec::with_capacity(CONTENT_LENGTH_SIZE);
+        LengthPrefixConfig::default().encode_header(msg.len() + trailer.len(), &mut header)?;
+        //头部、消息内容和校验值一起用 write_vectored 发送
+        Ok(write_all_vectored(self, Buf::chain(Buf::chain(header.as_slice(), msg), trailer.as_slice())).await?)
+    }
+
+    async fn send_chunked<R>(&mut self, src: &mut R) -> Result<u64, Error>
+    where
+        R: AsyncRead + Unpin + Send + ?Sized,
+    {
+        let mut total = 0u64;
+        let mut buf = vec![0u8; CHUNK_SIZE];
         loop {
-            let n = self.read(&mut buf).await?;
+            let n = src.read(&mut buf).await?;
+            //长度为 0 的块作为结束标识
+            self.send_len_bytes(&buf[..n]).await?;
             if n == 0 {
-                //end of Stream
                 break;
             }
-            msg.extend_from_slice(&buf[..n]);
+            total += n as u64;
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
+        Ok(total)
     }
+}
 
-    async fn read_len(&mut self) -> Result<String, io::Error> {
-        //读取内容长度
-        let mut content_len = [0u8; CONTENT_LENGTH_SIZE];
-        let n = self.read(&mut content_len).await?;
-        if n == 0 {
-            return Err(io::Error::new(ErrorKind::NotFound, "Not found content-length"));
+/// 任意 AsyncRead 的通用实现
+/// TcpStream、ReadHalf、OwnedReadHalf、UnixStream、VsockStream、DuplexStream 等均可直接使用
+#[async_trait]
+impl<T> SocketAsyncRecvTrait for T
+where
+    T: AsyncRead + Unpin + Send,
+{
+    async fn recv(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        Utf8Mode::Strict.decode(msg)
+    }
+
+    async fn recv_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    async fn read_len(&mut self) -> Result<String, Error> {
+        let msg = self.read_len_bytes().await?;
+        Utf8Mode::Strict.decode(msg)
+    }
+
+    async fn read_len_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.read_len_bytes().await?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    async fn read_line(&mut self) -> Result<String, Error> {
+        let msg = self.read_line_bytes().await?;
+        Utf8Mode::Strict.decode(msg)
+    }
+
+    async fn read_line_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.read_line_bytes().await?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    #[cfg(feature = "encoding")]
+    async fn recv_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.recv_bytes().await?;
+        decode_text(msg, encoding, Utf8Mode::Strict)
+    }
+
+    #[cfg(feature = "encoding")]
+    async fn read_len_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.read_len_bytes().await?;
+        decode_text(msg, encoding, Utf8Mode::Strict)
+    }
+
+    #[cfg(feature = "encoding")]
+    async fn read_line_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.read_line_bytes().await?;
+        decode_text(msg, encoding, Utf8Mode::Strict)
+    }
+
+    #[cfg(feature = "serde")]
+    async fn recv_msg<M>(&mut self) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        self.recv_msg_with(Format::Json).await
+    }
+
+    #[cfg(feature = "serde")]
+    async fn recv_msg_with<M>(&mut self, format: Format) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        let msg = self.read_len_bytes().await?;
+        format.decode(&msg)
+    }
+
+    #[cfg(feature = "prost")]
+    async fn recv_proto<M>(&mut self) -> Result<M, Error>
+    where
+        M: Message + Default,
+    {
+        self.recv_proto_with(&LengthPrefixConfig::varint()).await
+    }
+
+    #[cfg(feature = "prost")]
+    async fn recv_proto_with<M>(&mut self, config: &LengthPrefixConfig) -> Result<M, Error>
+    where
+        M: Message + Default,
+    {
+        let msg = self.read_len_with(config).await?;
+        M::decode(msg.as_slice()).map_err(|e| Error::Deserialize(Box::new(e)))
+    }
+
+    async fn read_compressed(&mut self, config: &CompressionConfig) -> Result<Vec<u8>, Error> {
+        let frame = self.read_len_bytes().await?;
+        config.decompress(&frame)
+    }
+
+    async fn read_versioned(&mut self, options: &FrameOptions) -> Result<Frame, Error> {
+        let frame = self.read_len_with(&options.length_config()).await?;
+        options.decode(frame)
+    }
+
+    async fn read_tagged(&mut self) -> Result<(u64, Vec<u8>), Error> {
+        let frame = self.read_len_bytes().await?;
+        split_tag(frame)
+    }
+
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    async fn read_len_checked(&mut self, checksum: Checksum) -> Result<Vec<u8>, Error> {
+        let frame = self.read_len_bytes().await?;
+        checksum.verify(frame)
+    }
+
+    async fn recv_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.recv_bytes_limit(usize::MAX).await
+    }
+
+    async fn read_len_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.read_len_with(&LengthPrefixConfig::default()).await
+    }
+
+    async fn read_len_with(&mut self, config: &LengthPrefixConfig) -> Result<Vec<u8>, Error> {
+        //读取头部，头部可能被拆分成多个 TCP 分段，必须读满
+        let mut header = vec![0u8; config.min_header_len()];
+        let mut header_size = 0;
+        while header_size < header.len() {
+            let n = self.read(&mut header[header_size..]).await?;
+            if n == 0 {
+                if header_size == 0 {
+                    return Err(Error::PeerClosed);
+                }
+                return Err(Error::TruncatedFrame { expected: header.len(), received: header_size });
+            }
+            header_size += n;
         }
-        let len = i32::from_be_bytes(content_len);
-        let len: usize = len.try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Convert Error i32 to usize"))?;
+        //varint 头部长度不固定，逐个byte读取直到解析出内容长度
+        let (header_len, frame_len) = loop {
+            if let Some(header) = config.decode_header(&header)? {
+                break header;
+            }
+            let mut byte = [0u8; 1];
+            if self.read(&mut byte).await? == 0 {
+                return Err(Error::TruncatedFrame { expected: header.len() + 1, received: header.len() });
+            }
+            header.push(byte[0]);
+        };
+        let len = frame_len - header_len;
 
-        //读取消息内容
+        //读取消息内容，最多只读 len 个byte，不能读到下一条消息
         let mut msg = vec![];
         let mut read_size = 0;
         let mut buf = [0u8; BUFFER_SIZE];
         while read_size < len {
-            let n = self.read(&mut buf).await?;
+            let end = BUFFER_SIZE.min(len - read_size);
+            let n = self.read(&mut buf[..end]).await?;
             if n == 0 {
-                //end of Stream
-                break;
+                //消息内容不完整，连接已关闭
+                return Err(Error::TruncatedFrame { expected: frame_len, received: header_len + read_size });
             }
             msg.extend_from_slice(&buf[..n]);
-            read_size = read_size + n;
+            read_size += n;
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
-    }
 
-    async fn read_line(&mut self) -> Result<String, io::Error> {
-        // 创建一个异步 reader，用于读取数据
-        let mut reader = BufReader::new(self);
-        let mut msg = String::new();
-        // 读取数据并处理
-        loop {
-            let mut buffer = String::new();
-            //read_line 读取到换行（包含换行）,或者EOF就返回
-            //所以这里用\n\n来做 数据结束标识
-            reader.read_line(&mut buffer).await?;
-            if buffer.trim().is_empty() {
-                break;
-            }
-            msg.push_str(&buffer);
-        }
-        //去掉数据末尾的\n\n
-        if !msg.is_empty() {
-            msg.remove(msg.len() - 1);
-        }
-        Ok(msg)
-    }
-    /*async fn recv(&mut self) -> Result<String, io::Error> {
-        let mut msg = vec![];
-        let mut buf = [0u8; BUFFER_SIZE];
-        loop {
-            ///read 超时5秒会报错
-            let res = timeout(Duration::from_secs(5), self.read(&mut buf)).await;
-            match res {
-                Ok(Ok(n)) => {
-                    // 处理读取到的数据
-                    if n == 0 {
-                        //end of Stream
-                        break;
-                    }
-                    msg.extend_from_slice(&buf[..n]);
-                }
-                Ok(Err(e)) => {
-                    // 处理读取错误
-                    return Err(e);
-                }
-                Err(e) => {
-                    // 处理超时
-                    return Err(Error::from(ErrorKind::TimedOut));
-                }
-            }
+        //去掉 initial_bytes_to_strip 指定的byte
+        let strip = config.strip_len(header_len);
+        if strip == header_len {
+            return Ok(msg);
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
-    }*/
-}
-
-#[async_trait]
-impl SocketAsyncSendTrait for WriteHalf<TcpStream> {
-    async fn send(&mut self, msg: String) -> Result<usize, io::Error> {
-        let bytes = msg.as_bytes();
-        let len = bytes.len();
-        let mut write_size = 0;
-        while write_size < len {
-            let mut end = write_size + BUFFER_SIZE;
-            if end > len {
-                end = len;
-            }
-            self.write_all(&bytes[write_size..end]).await?;
-            write_size = end;
-        };
-        Ok(write_size)
-    }
-
-    async fn send_len(&mut self, msg: String) -> Result<usize, io::Error> {
-        let mut bytes = vec![];
-        //头部插入4个byte的content-length值
-        let content_len: i32 = msg.as_bytes().len().try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Convert Error usize to i32"))?;
-        let content_len: [u8; CONTENT_LENGTH_SIZE] = content_len.to_be_bytes();
-        bytes.extend_from_slice(&content_len[..]);
-        //插入消息内容
-        bytes.extend_from_slice(msg.as_bytes());
-
-        let mut write_size = 0;
-        let len = bytes.len();
-        while write_size < len {
-            let mut end = write_size + BUFFER_SIZE;
-            if end > len {
-                end = len;
-            }
-            self.write_all(&bytes[write_size..end]).await?;
-            write_size = end;
-        };
-        Ok(write_size)
+        header.extend_from_slice(&msg);
+        Ok(header.split_off(strip))
     }
 
-    async fn send_line(&mut self, mut msg: String) -> Result<usize, io::Error> {
-        msg.push_str("\n\n");
-        self.send(msg).await
+    async fn read_line_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.read_line_bytes_limit(usize::MAX).await
     }
-}
 
-#[async_trait]
-impl SocketAsyncRecvTrait for ReadHalf<TcpStream> {
-    async fn recv(&mut self) -> Result<String, io::Error> {
+    async fn recv_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error> {
         let mut msg = vec![];
         let mut buf = [0u8; BUFFER_SIZE];
         loop {
@@ -231,198 +516,583 @@ impl SocketAsyncRecvTrait for ReadHalf<TcpStream> {
                 //end of Stream
                 break;
             }
+            if msg.len() + n > max {
+                return Err(FrameTooLarge::new(msg.len() + n, max, None).into());
+            }
             msg.extend_from_slice(&buf[..n]);
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
+        Ok(msg)
     }
 
-    async fn read_len(&mut self) -> Result<String, io::Error> {
-        //读取内容长度
-        let mut content_len = [0u8; CONTENT_LENGTH_SIZE];
-        let n = self.read(&mut content_len).await?;
-        if n == 0 {
-            return Err(io::Error::new(ErrorKind::NotFound, "Not found content-length"));
-        }
-        let len = i32::from_be_bytes(content_len);
-        let len: usize = len.try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Convert Error i32 to usize"))?;
+    async fn read_line_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error> {
+        self.read_delimited(&DelimiterConfig::blank_line().max_frame_length(max)).await
+    }
 
-        //读取消息内容
-        let mut msg = vec![];
-        let mut read_size = 0;
-        let mut buf = [0u8; BUFFER_SIZE];
-        while read_size < len {
-            let n = self.read(&mut buf).await?;
+    async fn read_delimited(&mut self, config: &DelimiterConfig) -> Result<Vec<u8>, Error> {
+        //每次调用都使用新的缓冲区，多读到的数据会丢失
+        let mut codec = DelimiterCodec::new(config.clone());
+        let mut buf = BytesMut::with_capacity(BUFFER_SIZE);
+        loop {
+            if let Some(msg) = codec.decode(&mut buf)? {
+                return Ok(Vec::from(msg));
+            }
+            buf.reserve(BUFFER_SIZE);
+            let n = self.read_buf(&mut buf).await?;
             if n == 0 {
                 //end of Stream
-                break;
+                return Ok(codec.decode_eof(&mut buf)?.map(Vec::from).unwrap_or_default());
             }
-            msg.extend_from_slice(&buf[..n]);
-            read_size = read_size + n;
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
     }
 
-    async fn read_line(&mut self) -> Result<String, io::Error> {
-        // 创建一个异步 reader，用于读取数据
-        let mut reader = BufReader::new(self);
-        let mut msg = String::new();
-        // 读取数据并处理
-        loop {
-            let mut buffer = String::new();
-            //read_line 读取到换行（包含换行）,或者EOF就返回
-            //所以这里用\n\n来做 数据结束标识
-            reader.read_line(&mut buffer).await?;
-            if buffer.trim().is_empty() {
-                break;
-            }
-            msg.push_str(&buffer);
+    fn read_chunked(&mut self) -> ChunkedReader<&mut Self> {
+        ChunkedReader::new(self)
+    }
+}
+
+
+/// 把 send_tagged 的消息拆分为请求 id 和消息内容
+pub(crate) fn split_tag(mut frame: Vec<u8>) -> Result<(u64, Vec<u8>), Error> {
+    if frame.len() < TAG_SIZE {
+        return Err(Error::InvalidLength("frame shorter than request id"));
+    }
+    let msg = frame.split_off(TAG_SIZE);
+    let mut id = [0u8; TAG_SIZE];
+    id.copy_from_slice(&frame);
+    Ok((u64::from_be_bytes(id), msg))
+}
+
+/// 用 write_vectored 写完 buf 中的全部数据（通常是 头部.chain(消息内容)），返回写入的byte数
+/// 不支持 vectored 写的流每次只会写第一个非空切片，结果和分开 write_all 相同
+async fn write_all_vectored<W, B>(writer: &mut W, mut buf: B) -> Result<usize, io::Error>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+    B: Buf,
+{
+    let total = buf.remaining();
+    while buf.has_remaining() {
+        let mut slices = [IoSlice::new(&[]); 4];
+        let cnt = buf.chunks_vectored(&mut slices);
+        let n = writer.write_vectored(&slices[..cnt]).await?;
+        if n == 0 {
+            return Err(io::Error::new(ErrorKind::WriteZero, "failed to write whole buffer"));
         }
-        //去掉数据末尾的\n\n
-        if !msg.is_empty() {
-            msg.remove(msg.len() - 1);
+        buf.advance(n);
+    }
+    Ok(total)
+}
+
+/// write_all_vectored 的 std 版本
+fn write_all_vectored_sync<W, B>(writer: &mut W, mut buf: B) -> Result<usize, io::Error>
+where
+    W: Write + ?Sized,
+    B: Buf,
+{
+    let total = buf.remaining();
+    while buf.has_remaining() {
+        let mut slices = [IoSlice::new(&[]); 4];
+        let cnt = buf.chunks_vectored(&mut slices);
+        match writer.write_vectored(&slices[..cnt]) {
+            Ok(0) => return Err(io::Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+            Ok(n) => buf.advance(n),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
-        Ok(msg)
     }
+    Ok(total)
 }
 
+/// read 的 std 版本，被信号中断（ErrorKind::Interrupted）时重试，与 read_exact 一致
+fn read_retry<R>(reader: &mut R, buf: &mut [u8]) -> Result<usize, io::Error>
+where
+    R: Read + ?Sized,
+{
+    loop {
+        match reader.read(buf) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
 
-/// std::net::TcpStream Trait实现
+/// std 同步流 Trait实现
 pub trait SocketSendTrait {
     /// 阻塞等待写通道关闭（read 返回 0）
-    fn send(&mut self, msg: String) -> Result<usize, io::Error>;
+    fn send(&mut self, msg: String) -> Result<usize, Error>;
     /// 无需等待写通道关闭
     /// read直接根据头部提供的content-length 来读取消息内容
     /// 头部插入content-length 标识
-    fn send_len(&mut self, msg: String) -> Result<usize, io::Error>;
+    fn send_len(&mut self, msg: String) -> Result<usize, Error>;
     /// 无需等待写通道关闭
     /// read直接根据空行（/n/n）来作为结束标识符
     /// 尾部插入空行（/n/n）
-    fn send_line(&mut self, msg: String) -> Result<usize, io::Error>;
+    fn send_line(&mut self, msg: String) -> Result<usize, Error>;
+    /// send 的二进制版本
+    fn send_bytes(&mut self, msg: &[u8]) -> Result<usize, Error>;
+    /// send_len 的二进制版本
+    fn send_len_bytes(&mut self, msg: &[u8]) -> Result<usize, Error>;
+    /// send_line 的二进制版本
+    fn send_line_bytes(&mut self, msg: &[u8]) -> Result<usize, Error>;
+    /// 尾部插入 config 指定的结束标识，设置了转义字符时先转义消息内容
+    fn send_delimited(&mut self, msg: &[u8], config: &DelimiterConfig) -> Result<usize, Error>;
+    /// 按 config 指定的头部格式发送 content-length + 消息内容
+    fn send_len_with(&mut self, msg: &[u8], config: &LengthPrefixConfig) -> Result<usize, Error>;
+    /// send 的转码版本，按 encoding 编码后发送
+    #[cfg(feature = "encoding")]
+    fn send_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error>;
+    /// send_len 的转码版本，content-length 为编码后的byte数
+    #[cfg(feature = "encoding")]
+    fn send_len_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error>;
+    /// send_line 的转码版本
+    #[cfg(feature = "encoding")]
+    fn send_line_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error>;
+    /// 把 msg 序列化为 JSON 后按 send_len 的格式发送
+    #[cfg(feature = "serde")]
+    fn send_msg<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized;
+    /// send_msg 的指定格式版本
+    #[cfg(feature = "serde")]
+    fn send_msg_with<M>(&mut self, msg: &M, format: Format) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized;
+    /// 发送 protobuf 消息，使用 varint 头部，与 Java/Go 的 writeDelimitedTo 兼容
+    #[cfg(feature = "prost")]
+    fn send_proto<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Message;
+    /// send_proto 的指定头部格式版本
+    #[cfg(feature = "prost")]
+    fn send_proto_with<M>(&mut self, msg: &M, config: &LengthPrefixConfig) -> Result<usize, Error>
+    where
+        M: Message;
+    /// 按 config 压缩后以 send_len 的格式发送，头部之后的第一个byte是压缩算法标识
+    /// 小于 config.threshold 的消息不压缩，返回实际发送的byte数
+    fn send_compressed(&mut self, msg: &[u8], config: &CompressionConfig) -> Result<usize, Error>;
+    /// 按 versioned 模块的帧格式发送，options 指定是否压缩、附加校验值
+    fn send_versioned(&mut self, frame: &Frame, options: &FrameOptions) -> Result<usize, Error>;
+    /// 以 send_len 的格式发送，消息内容之前附加 8 个byte（大端）的请求 id，用于 mux 模块匹配请求和回复
+    fn send_tagged(&mut self, id: u64, msg: &[u8]) -> Result<usize, Error>;
+    /// 以 send_len 的格式发送，消息内容之后附加 checksum 校验值，content-length 包含校验值的长度
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    fn send_len_checked(&mut self, msg: &[u8], checksum: Checksum) -> Result<usize, Error>;
 }
 
 pub trait SocketRecvTrait {
     /// 阻塞等待写通道关闭（read 返回 0）
-    fn recv(&mut self) -> Result<String, io::Error>;
+    fn recv(&mut self) -> Result<String, Error>;
     /// 无需等待写通道关闭
     /// 直接根据头部提供的content-length 来读取消息内容
-    fn read_len(&mut self) -> Result<String, io::Error>;
+    fn read_len(&mut self) -> Result<String, Error>;
     /// 无需等待写通道关闭
     /// 直接根据空行（/n/n）来作为结束标识符
-    fn read_line(&mut self) -> Result<String, io::Error>;
+    fn read_line(&mut self) -> Result<String, Error>;
+    /// recv 的宽松版本，非法 UTF-8 替换成 U+FFFD
+    fn recv_lossy(&mut self) -> Result<String, Error>;
+    /// read_len 的宽松版本，非法 UTF-8 替换成 U+FFFD
+    fn read_len_lossy(&mut self) -> Result<String, Error>;
+    /// read_line 的宽松版本，非法 UTF-8 替换成 U+FFFD
+    fn read_line_lossy(&mut self) -> Result<String, Error>;
+    /// recv 的转码版本，按 encoding 严格解码，非法数据返回 text::InvalidEncoding 错误
+    #[cfg(feature = "encoding")]
+    fn recv_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error>;
+    /// read_len 的转码版本，按 encoding 严格解码
+    #[cfg(feature = "encoding")]
+    fn read_len_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error>;
+    /// read_line 的转码版本，按 encoding 严格解码
+    #[cfg(feature = "encoding")]
+    fn read_line_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error>;
+    /// recv 的二进制版本，原样返回收到的字节
+    fn recv_bytes(&mut self) -> Result<Vec<u8>, Error>;
+    /// read_len 的二进制版本，原样返回收到的字节
+    fn read_len_bytes(&mut self) -> Result<Vec<u8>, Error>;
+    /// read_line 的二进制版本，原样返回收到的字节（不含结尾的\n\n），只精确匹配 \n\n
+    fn read_line_bytes(&mut self) -> Result<Vec<u8>, Error>;
+    /// 按 config 指定的头部格式读取消息内容
+    /// 只读取头部和 content-length 个byte，不会多读后续消息；消息内容不完整时返回 Error::TruncatedFrame
+    /// 超出 config.max_frame_length 时返回 FrameTooLarge，此时只读取了头部，剩余 remaining 个byte仍在连接中，
+    /// 调用方可以跳过这些byte继续读取下一条消息，或者直接关闭连接
+    fn read_len_with(&mut self, config: &LengthPrefixConfig) -> Result<Vec<u8>, Error>;
+    /// recv_bytes 的限制长度版本，超出 max 时返回 FrameTooLarge
+    /// 此时超出部分所在的一次读取已被丢弃，无法继续接收剩余数据，应关闭连接
+    fn recv_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error>;
+    /// read_line_bytes 的限制长度版本，超出 max 时返回 FrameTooLarge
+    /// 此时消息已被读取了一部分，无法确定下一条消息的位置，应关闭连接
+    fn read_line_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error>;
+    /// 根据 config 指定的结束标识读取消息，精确匹配结束标识，设置了转义字符时返回去掉转义后的内容
+    /// 对端关闭连接时剩余数据作为最后一条消息返回
+    fn read_delimited(&mut self, config: &DelimiterConfig) -> Result<Vec<u8>, Error>;
+    /// 按 read_len 的格式读取一条消息并从 JSON 反序列化，失败时返回 Error::Deserialize
+    #[cfg(feature = "serde")]
+    fn recv_msg<M>(&mut self) -> Result<M, Error>
+    where
+        M: DeserializeOwned;
+    /// recv_msg 的指定格式版本
+    #[cfg(feature = "serde")]
+    fn recv_msg_with<M>(&mut self, format: Format) -> Result<M, Error>
+    where
+        M: DeserializeOwned;
+    /// 读取 varint 头部的 protobuf 消息，与 Java/Go 的 parseDelimitedFrom 兼容，解码失败时返回 Error::Deserialize
+    #[cfg(feature = "prost")]
+    fn recv_proto<M>(&mut self) -> Result<M, Error>
+    where
+        M: Message + Default;
+    /// recv_proto 的指定头部格式版本
+    #[cfg(feature = "prost")]
+    fn recv_proto_with<M>(&mut self, config: &LengthPrefixConfig) -> Result<M, Error>
+    where
+        M: Message + Default;
+    /// 读取 send_compressed 发送的消息，按帧中的标识自动解压
+    /// 解压后超出 config.max_decompressed_size 时返回 FrameTooLarge，该帧已被完整读取，连接仍然可用
+    fn read_compressed(&mut self, config: &CompressionConfig) -> Result<Vec<u8>, Error>;
+    /// 读取 send_versioned 发送的帧，按帧中的标志位校验、解压
+    /// 主版本不支持时返回 Error::UnsupportedVersion，该帧已被完整读取，连接仍然可用
+    fn read_versioned(&mut self, options: &FrameOptions) -> Result<Frame, Error>;
+    /// 读取 send_tagged 发送的消息，返回请求 id 和消息内容
+    fn read_tagged(&mut self) -> Result<(u64, Vec<u8>), Error>;
+    /// 读取 send_len_checked 发送的消息并校验，返回去掉校验值后的消息内容
+    /// 校验值不一致时返回 Error::ChecksumMismatch，该帧已被完整读取，连接仍然可用
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    fn read_len_checked(&mut self, checksum: Checksum) -> Result<Vec<u8>, Error>;
 }
 
-impl SocketSendTrait for std::net::TcpStream {
-    fn send(&mut self, msg: String) -> Result<usize, io::Error> {
-        use std::io::Write;
-        let bytes = msg.as_bytes();
-        let len = bytes.len();
-        let mut write_size = 0;
-        while write_size < len {
-            let mut end = write_size + BUFFER_SIZE;
-            if end > len {
-                end = len;
-            }
-            self.write_all(&bytes[write_size..end])?;
-            write_size = end;
-        };
-        Ok(write_size)
-    }
-
-    fn send_len(&mut self, msg: String) -> Result<usize, io::Error> {
-        use std::io::Write;
-        let mut bytes = vec![];
-        //头部插入4个byte的content-length值
-        let content_len: i32 = msg.as_bytes().len().try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Convert Error usize to i32"))?;
-        let content_len: [u8; CONTENT_LENGTH_SIZE] = content_len.to_be_bytes();
-        bytes.extend_from_slice(&content_len[..]);
-        //插入消息内容
-        bytes.extend_from_slice(msg.as_bytes());
-
-        let mut write_size = 0;
-        let len = bytes.len();
-        while write_size < len {
-            let mut end = write_size + BUFFER_SIZE;
-            if end > len {
-                end = len;
-            }
-            self.write_all(&bytes[write_size..end])?;
-            write_size = end;
-        };
-        Ok(write_size)
+/// 任意 std::io::Write 的通用实现
+impl<T> SocketSendTrait for T
+where
+    T: Write,
+{
+    fn send(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_bytes(msg.as_bytes())
+    }
+
+    fn send_len(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_len_bytes(msg.as_bytes())
+    }
+
+    fn send_line(&mut self, msg: String) -> Result<usize, Error> {
+        self.send_line_bytes(msg.as_bytes())
+    }
+
+    fn send_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        //整块交给 write_all，由底层决定每次写多少，避免按 BUFFER_SIZE 切片产生多余的系统调用
+        self.write_all(msg)?;
+        Ok(msg.len())
+    }
+
+    fn send_len_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        self.send_len_with(msg, &LengthPrefixConfig::default())
     }
 
-    fn send_line(&mut self, mut msg: String) -> Result<usize, io::Error> {
-        msg.push_str("\n\n");
-        self.send(msg)
+    fn send_line_bytes(&mut self, msg: &[u8]) -> Result<usize, Error> {
+        self.send_delimited(msg, &DelimiterConfig::blank_line())
+    }
+
+    fn send_delimited(&mut self, msg: &[u8], config: &DelimiterConfig) -> Result<usize, Error> {
+        //设置了转义字符时先转义消息内容
+        let msg = config.escape_payload(msg);
+        //消息内容和尾部结束标识一起用 write_vectored 发送
+        Ok(write_all_vectored_sync(self, Buf::chain(msg.as_ref(), config.delimiter.as_slice()))?)
+    }
+
+    fn send_len_with(&mut self, msg: &[u8], config: &LengthPrefixConfig) -> Result<usize, Error> {
+        let mut header = Vec::with_capacity(config.min_header_len());
+        //头部只编码content-length值，消息内容不再拷贝
+        config.encode_header(msg.len(), &mut header)?;
+        //头部和消息内容用 write_vectored 一起发送
+        Ok(write_all_vectored_sync(self, Buf::chain(header.as_slice(), msg))?)
+    }
+
+    #[cfg(feature = "encoding")]
+    fn send_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error> {
+        let msg = encode_text(msg, encoding)?;
+        self.send_bytes(&msg)
+    }
+
+    #[cfg(feature = "encoding")]
+    fn send_len_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error> {
+        let msg = encode_text(msg, encoding)?;
+        self.send_len_bytes(&msg)
+    }
+
+    #[cfg(feature = "encoding")]
+    fn send_line_encoded(&mut self, msg: &str, encoding: &'static Encoding) -> Result<usize, Error> {
+        let msg = encode_text(msg, encoding)?;
+        self.send_line_bytes(&msg)
+    }
+
+    #[cfg(feature = "serde")]
+    fn send_msg<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized,
+    {
+        self.send_msg_with(msg, Format::Json)
+    }
+
+    #[cfg(feature = "serde")]
+    fn send_msg_with<M>(&mut self, msg: &M, format: Format) -> Result<usize, Error>
+    where
+        M: Serialize + Sync + ?Sized,
+    {
+        let msg = format.encode(msg)?;
+        self.send_len_bytes(&msg)
+    }
+
+    #[cfg(feature = "prost")]
+    fn send_proto<M>(&mut self, msg: &M) -> Result<usize, Error>
+    where
+        M: Message,
+    {
+        self.send_proto_with(msg, &LengthPrefixConfig::varint())
+    }
+
+    #[cfg(feature = "prost")]
+    fn send_proto_with<M>(&mut self, msg: &M, config: &LengthPrefixConfig) -> Result<usize, Error>
+    where
+        M: Message,
+    {
+        let msg = msg.encode_to_vec();
+        self.send_len_with(&msg, config)
+    }
+
+    fn send_compressed(&mut self, msg: &[u8], config: &CompressionConfig) -> Result<usize, Error> {
+        let (compression, payload) = config.compress(msg)?;
+        //content-length 包含压缩算法标识
+        let mut header = Vec::with_capacity(CONTENT_LENGTH_SIZE + 1);
+        LengthPrefixConfig::default().encode_header(payload.len() + 1, &mut header)?;
+        header.push(compression.id());
+        Ok(write_all_vectored_sync(self, Buf::chain(header.as_slice(), payload.as_ref()))?)
+    }
+
+    fn send_versioned(&mut self, frame: &Frame, options: &FrameOptions) -> Result<usize, Error> {
+        let frame = options.encode_parts(frame)?;
+        Ok(write_all_vectored_sync(self, Buf::chain(Buf::chain(frame.head.as_slice(), frame.payload.as_ref()), frame.trailer.as_slice()))?)
+    }
+
+    fn send_tagged(&mut self, id: u64, msg: &[u8]) -> Result<usize, Error> {
+        let mut header = Vec::with_capacity(CONTENT_LENGTH_SIZE + TAG_SIZE);
+        LengthPrefixConfig::default().encode_header(msg.len() + TAG_SIZE, &mut header)?;
+        header.extend_from_slice(&id.to_be_bytes());
+        Ok(write_all_vectored_sync(self, Buf::chain(header.as_slice(), msg))?)
+    }
+
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    fn send_len_checked(&mut self, msg: &[u8], checksum: Checksum) -> Result<usize, Error> {
+        let trailer = checksum.trailer(msg);
+        let mut header = Vec::with_capacity(CONTENT_LENGTH_SIZE);
+        LengthPrefixConfig::default().encode_header(msg.len() + trailer.len(), &mut header)?;
+        //头部、消息内容和校验值一起用 write_vectored 发送
+        Ok(write_all_vectored_sync(self, Buf::chain(Buf::chain(header.as_slice(), msg), trailer.as_slice()))?)
     }
 }
 
-impl SocketRecvTrait for std::net::TcpStream {
-    fn recv(&mut self) -> Result<String, io::Error> {
-        use std::io::Read;
+/// 任意 std::io::Read 的通用实现
+impl<T> SocketRecvTrait for T
+where
+    T: Read,
+{
+    fn recv(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes()?;
+        Utf8Mode::Strict.decode(msg)
+    }
+
+    fn recv_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.recv_bytes()?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    fn read_len(&mut self) -> Result<String, Error> {
+        let msg = self.read_len_bytes()?;
+        Utf8Mode::Strict.decode(msg)
+    }
+
+    fn read_len_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.read_len_bytes()?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    fn read_line(&mut self) -> Result<String, Error> {
+        let msg = self.read_line_bytes()?;
+        Utf8Mode::Strict.decode(msg)
+    }
+
+    fn read_line_lossy(&mut self) -> Result<String, Error> {
+        let msg = self.read_line_bytes()?;
+        Utf8Mode::Lossy.decode(msg)
+    }
+
+    #[cfg(feature = "encoding")]
+    fn recv_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.recv_bytes()?;
+        decode_text(msg, encoding, Utf8Mode::Strict)
+    }
+
+    #[cfg(feature = "encoding")]
+    fn read_len_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.read_len_bytes()?;
+        decode_text(msg, encoding, Utf8Mode::Strict)
+    }
+
+    #[cfg(feature = "encoding")]
+    fn read_line_encoded(&mut self, encoding: &'static Encoding) -> Result<String, Error> {
+        let msg = self.read_line_bytes()?;
+        decode_text(msg, encoding, Utf8Mode::Strict)
+    }
+
+    #[cfg(feature = "serde")]
+    fn recv_msg<M>(&mut self) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        self.recv_msg_with(Format::Json)
+    }
+
+    #[cfg(feature = "serde")]
+    fn recv_msg_with<M>(&mut self, format: Format) -> Result<M, Error>
+    where
+        M: DeserializeOwned,
+    {
+        let msg = self.read_len_bytes()?;
+        format.decode(&msg)
+    }
+
+    #[cfg(feature = "prost")]
+    fn recv_proto<M>(&mut self) -> Result<M, Error>
+    where
+        M: Message + Default,
+    {
+        self.recv_proto_with(&LengthPrefixConfig::varint())
+    }
+
+    #[cfg(feature = "prost")]
+    fn recv_proto_with<M>(&mut self, config: &LengthPrefixConfig) -> Result<M, Error>
+    where
+        M: Message + Default,
+    {
+        let msg = self.read_len_with(config)?;
+        M::decode(msg.as_slice()).map_err(|e| Error::Deserialize(Box::new(e)))
+    }
+
+    fn read_compressed(&mut self, config: &CompressionConfig) -> Result<Vec<u8>, Error> {
+        let frame = self.read_len_bytes()?;
+        config.decompress(&frame)
+    }
+
+    fn read_versioned(&mut self, options: &FrameOptions) -> Result<Frame, Error> {
+        let frame = self.read_len_with(&options.length_config())?;
+        options.decode(frame)
+    }
+
+    fn read_tagged(&mut self) -> Result<(u64, Vec<u8>), Error> {
+        let frame = self.read_len_bytes()?;
+        split_tag(frame)
+    }
+
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    fn read_len_checked(&mut self, checksum: Checksum) -> Result<Vec<u8>, Error> {
+        let frame = self.read_len_bytes()?;
+        checksum.verify(frame)
+    }
+
+    fn recv_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.recv_bytes_limit(usize::MAX)
+    }
+
+    fn read_len_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.read_len_with(&LengthPrefixConfig::default())
+    }
+
+    fn read_len_with(&mut self, config: &LengthPrefixConfig) -> Result<Vec<u8>, Error> {
+        //读取头部，头部可能被拆分成多个 TCP 分段，必须读满
+        let mut header = vec![0u8; config.min_header_len()];
+        let mut header_size = 0;
+        while header_size < header.len() {
+            let n = read_retry(self, &mut header[header_size..])?;
+            if n == 0 {
+                if header_size == 0 {
+                    return Err(Error::PeerClosed);
+                }
+                return Err(Error::TruncatedFrame { expected: header.len(), received: header_size });
+            }
+            header_size += n;
+        }
+        //varint 头部长度不固定，逐个byte读取直到解析出内容长度
+        let (header_len, frame_len) = loop {
+            if let Some(header) = config.decode_header(&header)? {
+                break header;
+            }
+            let mut byte = [0u8; 1];
+            if read_retry(self, &mut byte)? == 0 {
+                return Err(Error::TruncatedFrame { expected: header.len() + 1, received: header.len() });
+            }
+            header.push(byte[0]);
+        };
+        let len = frame_len - header_len;
+
+        //读取消息内容，最多只读 len 个byte，不能读到下一条消息
         let mut msg = vec![];
+        let mut read_size = 0;
         let mut buf = [0u8; BUFFER_SIZE];
-        loop {
-            let n = self.read(&mut buf)?;
+        while read_size < len {
+            let end = BUFFER_SIZE.min(len - read_size);
+            let n = read_retry(self, &mut buf[..end])?;
             if n == 0 {
-                //end of Stream
-                break;
+                //消息内容不完整，连接已关闭
+                return Err(Error::TruncatedFrame { expected: frame_len, received: header_len + read_size });
             }
             msg.extend_from_slice(&buf[..n]);
+            read_size += n;
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
-    }
 
-    fn read_len(&mut self) -> Result<String, io::Error> {
-        use std::io::Read;
-        //读取内容长度
-        let mut content_len = [0u8; CONTENT_LENGTH_SIZE];
-        let n = self.read(&mut content_len)?;
-        if n == 0 {
-            return Err(io::Error::new(ErrorKind::NotFound, "Not found content-length"));
+        //去掉 initial_bytes_to_strip 指定的byte
+        let strip = config.strip_len(header_len);
+        if strip == header_len {
+            return Ok(msg);
         }
-        let len = i32::from_be_bytes(content_len);
-        let len: usize = len.try_into().map_err(|_| io::Error::new(ErrorKind::InvalidData, "Convert Error i32 to usize"))?;
-        println!("len: {}", len);
+        header.extend_from_slice(&msg);
+        Ok(header.split_off(strip))
+    }
+
+    fn read_line_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        self.read_line_bytes_limit(usize::MAX)
+    }
 
-        //读取消息内容
+    fn recv_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error> {
         let mut msg = vec![];
-        let mut read_size = 0;
         let mut buf = [0u8; BUFFER_SIZE];
-        while read_size < len {
-            let n = self.read(&mut buf)?;
+        loop {
+            let n = read_retry(self, &mut buf)?;
             if n == 0 {
                 //end of Stream
                 break;
             }
+            if msg.len() + n > max {
+                return Err(FrameTooLarge::new(msg.len() + n, max, None).into());
+            }
             msg.extend_from_slice(&buf[..n]);
-            read_size = read_size + n;
         }
-        Ok(String::from_utf8_lossy(&msg).to_string())
+        Ok(msg)
     }
 
-    fn read_line(&mut self) -> Result<String, io::Error> {
-        use std::io::BufReader;
-        use std::io::BufRead;
-        // 创建一个异步 reader，用于读取数据
-        let mut reader = BufReader::new(self);
-        let mut msg = String::new();
-        // 读取数据并处理
+    fn read_line_bytes_limit(&mut self, max: usize) -> Result<Vec<u8>, Error> {
+        self.read_delimited(&DelimiterConfig::blank_line().max_frame_length(max))
+    }
+
+    fn read_delimited(&mut self, config: &DelimiterConfig) -> Result<Vec<u8>, Error> {
+        //每次调用都使用新的缓冲区，多读到的数据会丢失
+        let mut codec = DelimiterCodec::new(config.clone());
+        let mut buf = BytesMut::with_capacity(BUFFER_SIZE);
+        let mut chunk = [0u8; BUFFER_SIZE];
         loop {
-            let mut buffer = String::new();
-            //read_line 读取到换行（包含换行）,或者EOF就返回
-            //所以这里用\n\n来做 数据结束标识
-            reader.read_line(&mut buffer)?;
-            if buffer.trim().is_empty() {
-                break;
+            if let Some(msg) = codec.decode(&mut buf)? {
+                return Ok(Vec::from(msg));
             }
-            msg.push_str(&buffer);
-        }
-        //去掉数据末尾的\n\n
-        if !msg.is_empty() {
-            msg.remove(msg.len() - 1);
+            let n = read_retry(self, &mut chunk)?;
+            if n == 0 {
+                //end of Stream
+                return Ok(codec.decode_eof(&mut buf)?.map(Vec::from).unwrap_or_default());
+            }
+            buf.extend_from_slice(&chunk[..n]);
         }
-        Ok(msg)
     }
 }
 
@@ -1,7 +1,5 @@
-use std::{io};
-use std::io::Write;
-use std::net::{Shutdown, TcpStream};
-use socket::{SocketRecvTrait, SocketSendTrait};
+use std::io;
+use std::net::TcpStream;
 use tcp::socket::{SocketRecvTrait, SocketSendTrait};
 
 fn main() -> Result<(), io::Error> {
@@ -1,10 +1,10 @@
 use std::{io, thread};
-use std::net::{Shutdown, TcpListener, TcpStream};
 use std::time::Duration;
-use socket::{SocketRecvTrait, SocketSendTrait};
+use std::net::{TcpListener, TcpStream};
+use tcp::socket::{SocketRecvTrait, SocketSendTrait};
+use tcp::timeout::TimeoutStream;
 
-#[tokio::main]
-async fn main() -> Result<(), io::Error> {
+fn main() -> Result<(), io::Error> {
     let listener = TcpListener::bind("127.0.0.1:5005")?;
     println!("启动监听");
 
@@ -13,13 +13,11 @@ async fn main() -> Result<(), io::Error> {
     loop {
         if let Ok((stream, addr)) = listener.accept() {
             //由于Client写通道没有关闭,Read的问题,一直读不到EOF的问题 默认超时5秒
-            // stream.set_read_timeout(Some(Duration::new(5, 0)));
+            let stream = TimeoutStream::with_timeouts(stream, Some(Duration::new(5, 0)), Some(Duration::new(5, 0)));
             println!("Accepted connection from {}", addr);
 
             thread::spawn(move || {
-                let result = process_data(stream);
-                if result.is_err() {
-                    let error = result.err().unwrap();
+                if let Err(error) = process_data(stream) {
                     println!("处理数据错误：{:?}", error);
                 }
             });
@@ -27,7 +25,7 @@ async fn main() -> Result<(), io::Error> {
     }
 }
 
-fn process_data(mut stream: TcpStream) -> Result<(), io::Error> {
+fn process_data(mut stream: TimeoutStream<TcpStream>) -> Result<(), io::Error> {
     // 接收数据
     let request = stream.read_len()?;
     println!("Client Request: {}", &request);
@@ -0,0 +1,190 @@
+//! 文本消息解码
+//! recv/read_len/read_line 默认严格校验 UTF-8，非法数据返回 InvalidUtf8 错误，
+//! 需要把非法数据替换成 U+FFFD 时显式使用 *_lossy 方法或者 Utf8Mode::Lossy
+//! 开启 encoding feature 后可以通过 *_encoded 方法收发 GBK/GB18030/Big5 等编码的文本
+
+use std::{error, fmt};
+use std::io::{self, ErrorKind};
+use crate::error::Error;
+#[cfg(feature = "encoding")]
+use std::borrow::Cow;
+
+#[cfg(feature = "encoding")]
+pub use encoding_rs::{Encoding, BIG5, GB18030, GBK, UTF_8};
+
+/// 收到的消息转换为 String 的方式，对 *_encoded 方法的其他编码同样适用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Utf8Mode {
+    /// 非法 UTF-8 返回 InvalidUtf8 错误
+    #[default]
+    Strict,
+    /// 非法 UTF-8 替换成 U+FFFD（String::from_utf8_lossy）
+    Lossy,
+}
+
+impl Utf8Mode {
+    /// 按当前模式把消息转换为 String
+    pub fn decode(self, msg: Vec<u8>) -> Result<String, Error> {
+        match String::from_utf8(msg) {
+            Ok(text) => Ok(text),
+            Err(e) => match self {
+                Utf8Mode::Strict => Err(InvalidUtf8::from(e).into()),
+                Utf8Mode::Lossy => Ok(String::from_utf8_lossy(e.as_bytes()).into_owned()),
+            },
+        }
+    }
+}
+
+/// 消息不是合法的 UTF-8
+/// Trait 方法中以 Error::InvalidUtf8 返回；codec 中以 io::Error（ErrorKind::InvalidData）的形式返回，可以通过 InvalidUtf8::from_io_error 识别
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidUtf8 {
+    /// 第一个非法byte在消息中的偏移量，在此之前的数据都是合法的 UTF-8
+    pub offset: usize,
+    /// 非法byte序列的长度，None 表示消息在一个未完成的字符处结束
+    pub error_len: Option<usize>,
+    /// 收到的原始消息
+    pub bytes: Vec<u8>,
+}
+
+impl InvalidUtf8 {
+    /// 判断 io::Error 是否为 InvalidUtf8
+    pub fn from_io_error(err: &io::Error) -> Option<&InvalidUtf8> {
+        err.get_ref().and_then(|e| e.downcast_ref::<InvalidUtf8>())
+    }
+
+    /// 取出原始消息
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl From<std::string::FromUtf8Error> for InvalidUtf8 {
+    fn from(err: std::string::FromUtf8Error) -> Self {
+        let utf8_error = err.utf8_error();
+        InvalidUtf8 {
+            offset: utf8_error.valid_up_to(),
+            error_len: utf8_error.error_len(),
+            bytes: err.into_bytes(),
+        }
+    }
+}
+
+impl fmt::Display for InvalidUtf8 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid utf-8 at byte offset {} of {} byte message", self.offset, self.bytes.len())
+    }
+}
+
+impl error::Error for InvalidUtf8 {}
+
+impl From<InvalidUtf8> for io::Error {
+    fn from(err: InvalidUtf8) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
+
+/// 按 encoding 编码消息，返回编码后的byte，content-length 按编码后的byte数计算
+/// 只适用于兼容 ASCII 的编码（GBK、GB18030、Big5 等），UTF-16 按 encoding_rs 的约定输出 UTF-8
+/// 消息中存在该编码无法表示的字符时返回 ErrorKind::InvalidInput 的 Error::Io
+#[cfg(feature = "encoding")]
+pub fn encode_text<'a>(msg: &'a str, encoding: &'static Encoding) -> Result<Cow<'a, [u8]>, Error> {
+    let encoding = encoding.output_encoding();
+    if encoding == UTF_8 {
+        return Ok(Cow::Borrowed(msg.as_bytes()));
+    }
+    let mut encoder = encoding.new_encoder();
+    let mut dst = Vec::new();
+    let mut read_size = 0;
+    loop {
+        //按剩余数据的最大编码长度预留空间，保证每次都能继续编码
+        let max_len = encoder.max_buffer_length_from_utf8_without_replacement(msg.len() - read_size);
+        dst.reserve(max_len.unwrap_or(msg.len() - read_size));
+        let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(&msg[read_size..], &mut dst, true);
+        read_size += read;
+        match result {
+            encoding_rs::EncoderResult::InputEmpty => return Ok(Cow::Owned(dst)),
+            encoding_rs::EncoderResult::OutputFull => {}
+            encoding_rs::EncoderResult::Unmappable(c) => {
+                return Err(Error::Io(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("character {:?} at byte offset {} cannot be encoded in {}", c, read_size - c.len_utf8(), encoding.name()),
+                )));
+            }
+        }
+    }
+}
+
+/// 按 encoding 把收到的消息转换为 String
+/// Utf8Mode::Strict 时非法数据返回 InvalidEncoding 错误，Utf8Mode::Lossy 时替换成 U+FFFD
+#[cfg(feature = "encoding")]
+pub fn decode_text(msg: Vec<u8>, encoding: &'static Encoding, mode: Utf8Mode) -> Result<String, Error> {
+    if encoding == UTF_8 {
+        return mode.decode(msg);
+    }
+    if mode == Utf8Mode::Lossy {
+        let (text, _) = encoding.decode_without_bom_handling(&msg);
+        return Ok(text.into_owned());
+    }
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut dst = String::new();
+    let mut read_size = 0;
+    loop {
+        //按剩余数据的最大解码长度预留空间，保证每次都能继续解码
+        let max_len = decoder.max_utf8_buffer_length_without_replacement(msg.len() - read_size);
+        dst.reserve(max_len.unwrap_or(msg.len() - read_size));
+        let (result, read) = decoder.decode_to_string_without_replacement(&msg[read_size..], &mut dst, true);
+        read_size += read;
+        match result {
+            encoding_rs::DecoderResult::InputEmpty => return Ok(dst),
+            encoding_rs::DecoderResult::OutputFull => {}
+            encoding_rs::DecoderResult::Malformed(bad_len, extra) => {
+                let offset = read_size - bad_len as usize - extra as usize;
+                return Err(InvalidEncoding { encoding: encoding.name(), offset, bytes: msg }.into());
+            }
+        }
+    }
+}
+
+/// 消息不是 encoding 指定编码的合法数据
+/// Trait 方法中以 Error::InvalidEncoding 返回；codec 中以 io::Error（ErrorKind::InvalidData）的形式返回，可以通过 InvalidEncoding::from_io_error 识别
+#[cfg(feature = "encoding")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEncoding {
+    /// 编码名称，例如 "GBK"
+    pub encoding: &'static str,
+    /// 第一个非法byte在消息中的偏移量
+    pub offset: usize,
+    /// 收到的原始消息
+    pub bytes: Vec<u8>,
+}
+
+#[cfg(feature = "encoding")]
+impl InvalidEncoding {
+    /// 判断 io::Error 是否为 InvalidEncoding
+    pub fn from_io_error(err: &io::Error) -> Option<&InvalidEncoding> {
+        err.get_ref().and_then(|e| e.downcast_ref::<InvalidEncoding>())
+    }
+
+    /// 取出原始消息
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+#[cfg(feature = "encoding")]
+impl fmt::Display for InvalidEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} at byte offset {} of {} byte message", self.encoding, self.offset, self.bytes.len())
+    }
+}
+
+#[cfg(feature = "encoding")]
+impl error::Error for InvalidEncoding {}
+
+#[cfg(feature = "encoding")]
+impl From<InvalidEncoding> for io::Error {
+    fn from(err: InvalidEncoding) -> Self {
+        io::Error::new(ErrorKind::InvalidData, err)
+    }
+}
@@ -0,0 +1,567 @@
+//! 读写超时
+//! TimeoutStream 为包装的流设置默认的读写超时，所有 Trait 方法都会受其约束；
+//! *_timeout / *_deadline 方法为单次调用设置超时，超时返回 Error::Timeout，每个收发方法都有对应的版本（read_chunked 除外，
+//! 分块数据请对 TimeoutStream 包装后的流调用 read_chunked）
+//! 超时发生时消息可能只收发了一部分，连接上的数据已经不完整，此时应关闭连接；
+//! reader::MessageReader 的 *_timeout / *_deadline 方法例外，已经读到的数据保留在缓冲区中，超时后可以继续接收
+
+use std::future::Future;
+use std::io::{self, ErrorKind, IoSlice, Read, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{sleep, timeout_at, Instant, Sleep};
+#[cfg(any(feature = "crc32", feature = "xxhash"))]
+use crate::checksum::Checksum;
+use crate::compress::CompressionConfig;
+use crate::error::Error;
+use crate::frame::{DelimiterConfig, LengthPrefixConfig};
+use crate::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait, SocketRecvTrait, SocketSendTrait};
+use crate::versioned::{Frame, FrameOptions};
+#[cfg(feature = "encoding")]
+use crate::text::Encoding;
+#[cfg(feature = "serde")]
+use crate::message::Format;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "prost")]
+use prost::Message;
+
+/// 带默认读写超时的流
+/// 超时按空闲时间计算：每次读写有进展时重新计时，适合长连接上等待对端的场景
+/// 同时实现了 tokio 的 AsyncRead/AsyncWrite 和 std 的 Read/Write（std 流需要实现 SyncTimeout）
+#[derive(Debug)]
+pub struct TimeoutStream<S> {
+    inner: S,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    read_sleep: Option<Pin<Box<Sleep>>>,
+    write_sleep: Option<Pin<Box<Sleep>>>,
+    /// std 流的超时是否已经设置到 socket 上
+    read_applied: bool,
+    write_applied: bool,
+}
+
+impl<S> TimeoutStream<S> {
+    /// 不设置超时，之后可以通过 set_read_timeout/set_write_timeout 设置
+    pub fn new(inner: S) -> Self {
+        TimeoutStream::with_timeouts(inner, None, None)
+    }
+
+    pub fn with_timeouts(inner: S, read_timeout: Option<Duration>, write_timeout: Option<Duration>) -> Self {
+        TimeoutStream {
+            inner,
+            read_timeout,
+            write_timeout,
+            read_sleep: None,
+            write_sleep: None,
+            read_applied: false,
+            write_applied: false,
+        }
+    }
+
+    /// 设置读超时，None 表示不超时
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+        self.read_sleep = None;
+        self.read_applied = false;
+    }
+
+    /// 设置写超时，None 表示不超时
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+        self.write_sleep = None;
+        self.write_applied = false;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+/// 操作返回 Pending 时开始计时，超时返回 Error::Timeout；操作完成时清除计时器
+fn poll_timeout<T>(
+    sleep_slot: &mut Option<Pin<Box<Sleep>>>,
+    timeout: Option<Duration>,
+    cx: &mut Context<'_>,
+    poll: Poll<io::Result<T>>,
+) -> Poll<io::Result<T>> {
+    match poll {
+        Poll::Ready(result) => {
+            *sleep_slot = None;
+            Poll::Ready(result)
+        }
+        Poll::Pending => {
+            let timeout = match timeout {
+                Some(timeout) => timeout,
+                None => return Poll::Pending,
+            };
+            let timer = sleep_slot.get_or_insert_with(|| Box::pin(sleep(timeout)));
+            match timer.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    *sleep_slot = None;
+                    Poll::Ready(Err(Error::Timeout.into()))
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S> AsyncRead for TimeoutStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<Result<(), io::Error>> {
+        let me = self.get_mut();
+        let poll = Pin::new(&mut me.inner).poll_read(cx, buf);
+        poll_timeout(&mut me.read_sleep, me.read_timeout, cx, poll)
+    }
+}
+
+impl<S> AsyncWrite for TimeoutStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let me = self.get_mut();
+        let poll = Pin::new(&mut me.inner).poll_write(cx, buf);
+        poll_timeout(&mut me.write_sleep, me.write_timeout, cx, poll)
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[IoSlice<'_>]) -> Poll<Result<usize, io::Error>> {
+        let me = self.get_mut();
+        let poll = Pin::new(&mut me.inner).poll_write_vectored(cx, bufs);
+        poll_timeout(&mut me.write_sleep, me.write_timeout, cx, poll)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let me = self.get_mut();
+        let poll = Pin::new(&mut me.inner).poll_flush(cx);
+        poll_timeout(&mut me.write_sleep, me.write_timeout, cx, poll)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let me = self.get_mut();
+        let poll = Pin::new(&mut me.inner).poll_shutdown(cx);
+        poll_timeout(&mut me.write_sleep, me.write_timeout, cx, poll)
+    }
+}
+
+/// 支持设置读写超时的 std 同步流
+pub trait SyncTimeout {
+    fn read_timeout(&self) -> io::Result<Option<Duration>>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn write_timeout(&self) -> io::Result<Option<Duration>>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl SyncTimeout for std::net::TcpStream {
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        std::net::TcpStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::net::TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        std::net::TcpStream::write_timeout(self)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::net::TcpStream::set_write_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl SyncTimeout for std::os::unix::net::UnixStream {
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        std::os::unix::net::UnixStream::read_timeout(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        std::os::unix::net::UnixStream::write_timeout(self)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_write_timeout(self, timeout)
+    }
+}
+
+/// std 流超时时返回 WouldBlock（unix）或 TimedOut（windows），统一转换为 Error::Timeout
+fn map_sync_timeout(err: io::Error) -> io::Error {
+    match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => Error::Timeout.into(),
+        _ => err,
+    }
+}
+
+impl<S> Read for TimeoutStream<S>
+where
+    S: Read + SyncTimeout,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.read_applied {
+            self.inner.set_read_timeout(self.read_timeout)?;
+            self.read_applied = true;
+        }
+        self.inner.read(buf).map_err(map_sync_timeout)
+    }
+}
+
+impl<S> Write for TimeoutStream<S>
+where
+    S: Write + SyncTimeout,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.write_applied {
+            self.inner.set_write_timeout(self.write_timeout)?;
+            self.write_applied = true;
+        }
+        self.inner.write(buf).map_err(map_sync_timeout)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        if !self.write_applied {
+            self.inner.set_write_timeout(self.write_timeout)?;
+            self.write_applied = true;
+        }
+        self.inner.write_vectored(bufs).map_err(map_sync_timeout)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().map_err(map_sync_timeout)
+    }
+}
+
+/// 在截止时间之前完成读写的 std 流
+/// 每次读写前把 socket 超时设置为剩余时间，结束后恢复原来的超时设置
+struct SyncDeadline<'a, S: SyncTimeout> {
+    inner: &'a mut S,
+    deadline: std::time::Instant,
+    read_timeout: Option<Option<Duration>>,
+    write_timeout: Option<Option<Duration>>,
+}
+
+impl<'a, S: SyncTimeout> SyncDeadline<'a, S> {
+    fn new(inner: &'a mut S, deadline: std::time::Instant) -> Self {
+        SyncDeadline { inner, deadline, read_timeout: None, write_timeout: None }
+    }
+
+    /// 剩余时间，已经超过截止时间时返回 Error::Timeout
+    fn remaining(&self) -> io::Result<Duration> {
+        let remaining = self.deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Timeout.into());
+        }
+        Ok(remaining)
+    }
+
+    fn apply_read_timeout(&mut self) -> io::Result<()> {
+        let remaining = self.remaining()?;
+        if self.read_timeout.is_none() {
+            self.read_timeout = Some(self.inner.read_timeout()?);
+        }
+        self.inner.set_read_timeout(Some(remaining))
+    }
+
+    fn apply_write_timeout(&mut self) -> io::Result<()> {
+        let remaining = self.remaining()?;
+        if self.write_timeout.is_none() {
+            self.write_timeout = Some(self.inner.write_timeout()?);
+        }
+        self.inner.set_write_timeout(Some(remaining))
+    }
+}
+
+impl<S: SyncTimeout + Read> Read for SyncDeadline<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.apply_read_timeout()?;
+        self.inner.read(buf).map_err(map_sync_timeout)
+    }
+}
+
+impl<S: SyncTimeout + Write> Write for SyncDeadline<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.apply_write_timeout()?;
+        self.inner.write(buf).map_err(map_sync_timeout)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.apply_write_timeout()?;
+        self.inner.write_vectored(bufs).map_err(map_sync_timeout)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush().map_err(map_sync_timeout)
+    }
+}
+
+impl<S: SyncTimeout> Drop for SyncDeadline<'_, S> {
+    fn drop(&mut self) {
+        //恢复原来的超时设置
+        if let Some(timeout) = self.read_timeout {
+            let _ = self.inner.set_read_timeout(timeout);
+        }
+        if let Some(timeout) = self.write_timeout {
+            let _ = self.inner.set_write_timeout(timeout);
+        }
+    }
+}
+
+/// 在截止时间之前完成 fut，超时返回 Error::Timeout
+pub(crate) async fn with_deadline<T, F>(deadline: Instant, fut: F) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+{
+    timeout_at(deadline, fut).await.map_err(|_| Error::Timeout)?
+}
+
+/// 有超时版本的发送方法，每行为 `方法 => 超时版本, 截止时间版本 (参数) -> 返回值`
+/// 把列表追加到 $gen 的输入之后展开，异步和 std 的超时 Trait 由同一份列表生成
+macro_rules! send_timeout_methods {
+    ($gen:ident! { $($head:tt)* }) => {
+        $gen! {
+            $($head)*
+            send => send_timeout, send_deadline (msg: String) -> usize;
+            send_len => send_len_timeout, send_len_deadline (msg: String) -> usize;
+            send_line => send_line_timeout, send_line_deadline (msg: String) -> usize;
+            send_bytes => send_bytes_timeout, send_bytes_deadline (msg: &[u8]) -> usize;
+            send_len_bytes => send_len_bytes_timeout, send_len_bytes_deadline (msg: &[u8]) -> usize;
+            send_line_bytes => send_line_bytes_timeout, send_line_bytes_deadline (msg: &[u8]) -> usize;
+            send_delimited => send_delimited_timeout, send_delimited_deadline (msg: &[u8], config: &DelimiterConfig) -> usize;
+            send_len_with => send_len_with_timeout, send_len_with_deadline (msg: &[u8], config: &LengthPrefixConfig) -> usize;
+            #[cfg(feature = "encoding")]
+            send_encoded => send_encoded_timeout, send_encoded_deadline (msg: &str, encoding: &'static Encoding) -> usize;
+            #[cfg(feature = "encoding")]
+            send_len_encoded => send_len_encoded_timeout, send_len_encoded_deadline (msg: &str, encoding: &'static Encoding) -> usize;
+            #[cfg(feature = "encoding")]
+            send_line_encoded => send_line_encoded_timeout, send_line_encoded_deadline (msg: &str, encoding: &'static Encoding) -> usize;
+            #[cfg(feature = "serde")]
+            send_msg => send_msg_timeout, send_msg_deadline <M> (msg: &M) -> usize
+                where M: Serialize + Sync + ?Sized;
+            #[cfg(feature = "serde")]
+            send_msg_with => send_msg_with_timeout, send_msg_with_deadline <M> (msg: &M, format: Format) -> usize
+                where M: Serialize + Sync + ?Sized;
+            #[cfg(feature = "prost")]
+            send_proto => send_proto_timeout, send_proto_deadline <M> (msg: &M) -> usize
+                where M: Message;
+            #[cfg(feature = "prost")]
+            send_proto_with => send_proto_with_timeout, send_proto_with_deadline <M> (msg: &M, config: &LengthPrefixConfig) -> usize
+                where M: Message;
+            send_compressed => send_compressed_timeout, send_compressed_deadline (msg: &[u8], config: &CompressionConfig) -> usize;
+            send_versioned => send_versioned_timeout, send_versioned_deadline (frame: &Frame, options: &FrameOptions) -> usize;
+            send_tagged => send_tagged_timeout, send_tagged_deadline (id: u64, msg: &[u8]) -> usize;
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            send_len_checked => send_len_checked_timeout, send_len_checked_deadline (msg: &[u8], checksum: Checksum) -> usize;
+        }
+    };
+}
+
+/// 有超时版本的接收方法，格式同 send_timeout_methods
+/// 异步和 std 的超时 Trait、reader::MessageReader 由同一份列表生成
+macro_rules! recv_timeout_methods {
+    ($gen:ident! { $($head:tt)* }) => {
+        $gen! {
+            $($head)*
+            recv => recv_timeout, recv_deadline () -> String;
+            read_len => read_len_timeout, read_len_deadline () -> String;
+            read_line => read_line_timeout, read_line_deadline () -> String;
+            recv_bytes => recv_bytes_timeout, recv_bytes_deadline () -> Vec<u8>;
+            read_len_bytes => read_len_bytes_timeout, read_len_bytes_deadline () -> Vec<u8>;
+            read_line_bytes => read_line_bytes_timeout, read_line_bytes_deadline () -> Vec<u8>;
+            recv_lossy => recv_lossy_timeout, recv_lossy_deadline () -> String;
+            read_len_lossy => read_len_lossy_timeout, read_len_lossy_deadline () -> String;
+            read_line_lossy => read_line_lossy_timeout, read_line_lossy_deadline () -> String;
+            #[cfg(feature = "encoding")]
+            recv_encoded => recv_encoded_timeout, recv_encoded_deadline (encoding: &'static Encoding) -> String;
+            #[cfg(feature = "encoding")]
+            read_len_encoded => read_len_encoded_timeout, read_len_encoded_deadline (encoding: &'static Encoding) -> String;
+            #[cfg(feature = "encoding")]
+            read_line_encoded => read_line_encoded_timeout, read_line_encoded_deadline (encoding: &'static Encoding) -> String;
+            read_len_with => read_len_with_timeout, read_len_with_deadline (config: &LengthPrefixConfig) -> Vec<u8>;
+            recv_bytes_limit => recv_bytes_limit_timeout, recv_bytes_limit_deadline (max: usize) -> Vec<u8>;
+            read_line_bytes_limit => read_line_bytes_limit_timeout, read_line_bytes_limit_deadline (max: usize) -> Vec<u8>;
+            read_delimited => read_delimited_timeout, read_delimited_deadline (config: &DelimiterConfig) -> Vec<u8>;
+            #[cfg(feature = "serde")]
+            recv_msg => recv_msg_timeout, recv_msg_deadline <M> () -> M
+                where M: DeserializeOwned;
+            #[cfg(feature = "serde")]
+            recv_msg_with => recv_msg_with_timeout, recv_msg_with_deadline <M> (format: Format) -> M
+                where M: DeserializeOwned;
+            #[cfg(feature = "prost")]
+            recv_proto => recv_proto_timeout, recv_proto_deadline <M> () -> M
+                where M: Message + Default;
+            #[cfg(feature = "prost")]
+            recv_proto_with => recv_proto_with_timeout, recv_proto_with_deadline <M> (config: &LengthPrefixConfig) -> M
+                where M: Message + Default;
+            read_compressed => read_compressed_timeout, read_compressed_deadline (config: &CompressionConfig) -> Vec<u8>;
+            read_versioned => read_versioned_timeout, read_versioned_deadline (options: &FrameOptions) -> Frame;
+            read_tagged => read_tagged_timeout, read_tagged_deadline () -> (u64, Vec<u8>);
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            read_len_checked => read_len_checked_timeout, read_len_checked_deadline (checksum: Checksum) -> Vec<u8>;
+        }
+    };
+}
+
+pub(crate) use recv_timeout_methods;
+
+/// 生成异步超时 Trait 和它对 $base 的通用实现，截止时间版本通过 with_deadline 调用 $base 的方法
+macro_rules! async_timeout_trait {
+    (
+        $(#[$trait_attr:meta])*
+        pub trait $name:ident => $base:ident;
+        $(#[$impl_attr:meta])*
+        impl for T where T: $tb0:ident $(+ $tb:ident)*;
+        $(
+            $(#[$attr:meta])*
+            $method:ident => $timeout:ident, $deadline:ident $(<$g:ident>)? ($($arg:ident: $ty:ty),*) -> $ret:ty
+                $(where $wg:ident: $b0:ident $(+ $b:ident)* $(+ ?$unsized:ident)?)?;
+        )*
+    ) => {
+        $(#[$trait_attr])*
+        #[async_trait]
+        pub trait $name {
+            $(
+                #[doc = concat!(stringify!($method), " 的超时版本")]
+                $(#[$attr])*
+                async fn $timeout $(<$g>)? (&mut self, $($arg: $ty,)* timeout: Duration) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?;
+                #[doc = concat!(stringify!($method), " 的截止时间版本")]
+                $(#[$attr])*
+                async fn $deadline $(<$g>)? (&mut self, $($arg: $ty,)* deadline: Instant) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?;
+            )*
+        }
+
+        $(#[$impl_attr])*
+        #[async_trait]
+        impl<T> $name for T
+        where
+            T: $tb0 $(+ $tb)*,
+        {
+            $(
+                $(#[$attr])*
+                async fn $timeout $(<$g>)? (&mut self, $($arg: $ty,)* timeout: Duration) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?
+                {
+                    self.$deadline($($arg,)* Instant::now() + timeout).await
+                }
+
+                $(#[$attr])*
+                async fn $deadline $(<$g>)? (&mut self, $($arg: $ty,)* deadline: Instant) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?
+                {
+                    with_deadline(deadline, $base::$method(self, $($arg),*)).await
+                }
+            )*
+        }
+    };
+}
+
+/// 生成 std 超时 Trait 和它的通用实现，截止时间版本在 SyncDeadline 上调用 $base 的方法
+macro_rules! std_timeout_trait {
+    (
+        $(#[$trait_attr:meta])*
+        pub trait $name:ident => $base:ident;
+        $(#[$impl_attr:meta])*
+        impl for T where T: $tb0:ident $(+ $tb:ident)*;
+        $(
+            $(#[$attr:meta])*
+            $method:ident => $timeout:ident, $deadline:ident $(<$g:ident>)? ($($arg:ident: $ty:ty),*) -> $ret:ty
+                $(where $wg:ident: $b0:ident $(+ $b:ident)* $(+ ?$unsized:ident)?)?;
+        )*
+    ) => {
+        $(#[$trait_attr])*
+        pub trait $name {
+            $(
+                #[doc = concat!(stringify!($method), " 的超时版本")]
+                $(#[$attr])*
+                fn $timeout $(<$g>)? (&mut self, $($arg: $ty,)* timeout: Duration) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?;
+                #[doc = concat!(stringify!($method), " 的截止时间版本")]
+                $(#[$attr])*
+                fn $deadline $(<$g>)? (&mut self, $($arg: $ty,)* deadline: std::time::Instant) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?;
+            )*
+        }
+
+        $(#[$impl_attr])*
+        impl<T> $name for T
+        where
+            T: $tb0 $(+ $tb)*,
+        {
+            $(
+                $(#[$attr])*
+                fn $timeout $(<$g>)? (&mut self, $($arg: $ty,)* timeout: Duration) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?
+                {
+                    self.$deadline($($arg,)* std::time::Instant::now() + timeout)
+                }
+
+                $(#[$attr])*
+                fn $deadline $(<$g>)? (&mut self, $($arg: $ty,)* deadline: std::time::Instant) -> Result<$ret, Error>
+                $(where $wg: $b0 $(+ $b)* $(+ ?$unsized)?)?
+                {
+                    $base::$method(&mut SyncDeadline::new(self, deadline), $($arg),*)
+                }
+            )*
+        }
+    };
+}
+
+send_timeout_methods!(async_timeout_trait! {
+    /// tokio 异步流的单次发送超时
+    /// *_timeout 从调用时开始计时，*_deadline 指定截止时间，超时返回 Error::Timeout
+    /// 超时会取消正在进行的读写，接收超时后需要继续使用连接时，请使用 reader::MessageReader 的 *_timeout / *_deadline 方法
+    pub trait SocketAsyncSendTimeoutTrait => SocketAsyncSendTrait;
+    /// 任意实现了 SocketAsyncSendTrait 的流的通用实现
+    impl for T where T: SocketAsyncSendTrait + Send;
+    send_chunked => send_chunked_timeout, send_chunked_deadline <R> (src: &mut R) -> u64
+        where R: AsyncRead + Unpin + Send + ?Sized;
+});
+
+recv_timeout_methods!(async_timeout_trait! {
+    pub trait SocketAsyncRecvTimeoutTrait => SocketAsyncRecvTrait;
+    /// 任意实现了 SocketAsyncRecvTrait 的流的通用实现
+    impl for T where T: SocketAsyncRecvTrait + Send;
+});
+
+send_timeout_methods!(std_timeout_trait! {
+    /// std 同步流的单次发送超时，通过临时修改 socket 的超时设置实现
+    pub trait SocketSendTimeoutTrait => SocketSendTrait;
+    /// 任意支持设置超时的 std 流的通用实现
+    impl for T where T: Write + SyncTimeout;
+});
+
+recv_timeout_methods!(std_timeout_trait! {
+    pub trait SocketRecvTimeoutTrait => SocketRecvTrait;
+    impl for T where T: Read + SyncTimeout;
+});
@@ -1,6 +1,5 @@
-use tokio::io::{self, AsyncWriteExt, ReadHalf};
+use tokio::io::{self, ReadHalf};
 use tokio::net::TcpStream;
-use socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
 use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
 
 #[tokio::main]
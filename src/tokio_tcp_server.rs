@@ -2,9 +2,8 @@ use tokio::{
     io::{self},
     net::TcpListener,
 };
-use tokio::io::{ReadHalf, WriteHalf};
 use tokio::net::TcpStream;
-use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::detect::{detect, DetectConfig};
 
 #[tokio::main]
 async fn main() -> Result<(), io::Error> {
@@ -15,26 +14,20 @@ async fn main() -> Result<(), io::Error> {
         let (stream, addr) = listener.accept().await?;
         println!("Accepted connection from {}", addr);
 
-        let (rd, wr) = io::split(stream);
-
         //处理数据
-        process_data(rd, wr).await?;
+        process_data(stream).await?;
     }
 }
 
-async fn process_data(mut rd: ReadHalf<TcpStream>, mut wr: WriteHalf<TcpStream>) -> Result<(), io::Error> {
+async fn process_data(stream: TcpStream) -> Result<(), io::Error> {
+    // 根据前几个byte识别客户端使用的是 send_len、send_line 还是 send + shutdown
+    let mut reader = detect(stream, &DetectConfig::default()).await?;
     // 接收数据
-    let request = rd.read_len().await?;
-    println!("Client Request: {}", &request);
-    // 发送回复
+    let request = reader.recv().await?;
+    println!("Client Request ({:?}): {}", reader.framing(), &request);
+    // 发送回复，使用与客户端相同的分帧方式
     let response = format!("The server receives your message, msg: {}", &request);
-    // send_len 通过content-length 标识数据长度
-    wr.send_len(response).await?;
-    // send_line 通过空行\n\n 作为结束标识
-    // wr.send_line(response).await?;
-    //关闭 TcpStream 的写操作 否则 read会阻塞 无法返回0
-    //这里可以不关,因为到此程序已经结束了,整个tcp连接都会关闭
-    // wr.send(response).await?;
-    // wr.shutdown().await?;
+    reader.send(response).await?;
+    //这里可以不关闭写通道,因为到此程序已经结束了,整个tcp连接都会关闭
     Ok(())
-}
\ No newline at end of file
+}
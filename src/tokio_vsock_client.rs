@@ -1,38 +1,38 @@
 use std::net::Shutdown;
 use tokio::io;
 use tokio_vsock::VsockStream;
-use tcp::vsock::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::vsock::{Connection, Error, Framing, Handshake, SocketAsyncRecvTrait, SocketAsyncSendTrait};
 
 #[tokio::main]
 async fn main() -> Result<(), io::Error> {
     let cid = AWS_PARENT_CID;
     let port = 5000;
-    let mut stream = VsockStream::connect(cid, port).await.expect(&format!("vsock connect error,cid:{} port:{}", cid, port));
+    let stream = VsockStream::connect(cid, port).await.unwrap_or_else(|_| panic!("vsock connect error,cid:{} port:{}", cid, port));
     println!("连接成功");
 
+    //握手协商分帧方式，不需要和服务端事先约定使用 send_len 还是 send_line
+    let handshake = Handshake::default().framings(vec![Framing::Len, Framing::Line]);
+    let mut conn = Connection::client(stream, &handshake).await?;
+    println!("握手成功 {:?}", conn.settings());
+
     //发送数据
     // let msg = "abcdefghijklmnop";
     let msg = "abcdefghijklmnopqrstuvwxyz";
-    // send_len 通过content-length 标识数据长度
-    stream.send_len(msg.to_string()).await?;
-    // send_line 通过空行\n\n 作为结束标识
-    // stream.send_line(msg.to_string()).await?;
-    //关闭 TcpStream 的写操作 否则 read会阻塞 无法返回0
-    // stream.send(msg.to_string()).await?;
-    // stream.shutdown().await?;
+    conn.send_frame(msg.as_bytes()).await?;
 
-    process_data(stream).await?;
+    process_data(conn).await?;
 
     Ok(())
 }
 
-pub async fn process_data(mut stream: VsockStream) -> Result<(), io::Error> {
+pub async fn process_data(mut conn: Connection<VsockStream>) -> Result<(), io::Error> {
     // 接收回复
-    let response = stream.read_len().await?;
-    println!("Server Response: {}", &response);
+    let response = conn.recv_frame().await?;
+    println!("Server Response: {}", String::from_utf8_lossy(&response));
     Ok(())
 }
 
+#[allow(dead_code)]
 async fn send_test() -> Result<String, io::Error> {
     println!("发送消息测试");
     let cid = AWS_PARENT_CID;
@@ -47,7 +47,6 @@ async fn send_test() -> Result<String, io::Error> {
 }
 
 
-const VMADDR_CID_ANY: u32 = 0xFFFFFFFF;
 const AWS_PARENT_CID: u32 = 2;
 
 pub struct VsockClient {
@@ -57,25 +56,25 @@ pub struct VsockClient {
 impl VsockClient {
     pub async fn new(cid: u32, port: u32) -> Self {
         println!("new client cid:{},port:{}", cid, port);
-        let stream = VsockStream::connect(cid, port).await.expect(&format!("vsock connect error,cid:{} port:{}", cid, port));
+        let stream = VsockStream::connect(cid, port).await.unwrap_or_else(|_| panic!("vsock connect error,cid:{} port:{}", cid, port));
         VsockClient {
             stream,
         }
     }
 
-    pub async fn send_line(&mut self, msg: String) -> Result<usize, io::Error> {
+    pub async fn send_line(&mut self, msg: String) -> Result<usize, Error> {
         self.stream.send_line(msg).await
     }
 
-    pub async fn send_len(&mut self, msg: String) -> Result<usize, io::Error> {
+    pub async fn send_len(&mut self, msg: String) -> Result<usize, Error> {
         self.stream.send_len(msg).await
     }
 
-    pub async fn read_len(&mut self) -> Result<String, io::Error> {
+    pub async fn read_len(&mut self) -> Result<String, Error> {
         self.stream.read_len().await
     }
 
-    pub async fn read_line(&mut self) -> Result<String, io::Error> {
+    pub async fn read_line(&mut self) -> Result<String, Error> {
         self.stream.read_line().await
     }
 
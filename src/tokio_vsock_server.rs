@@ -1,6 +1,6 @@
 use tokio::io;
 use tokio_vsock::{VsockListener, VsockStream};
-use tcp::vsock::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::vsock::{Connection, Framing, Handshake};
 
 #[tokio::main]
 async fn main() -> Result<(), io::Error> {
@@ -10,7 +10,6 @@ async fn main() -> Result<(), io::Error> {
 pub struct VsockServer {}
 
 const VMADDR_CID_ANY: u32 = 0xFFFFFFFF;
-const AWS_PARENT_CID: u32 = 2;
 
 impl VsockServer {
     ///初始化监听端口
@@ -30,15 +29,20 @@ impl VsockServer {
     }
 }
 
-pub async fn process_data(mut stream: VsockStream) -> Result<(), io::Error> {
+pub async fn process_data(stream: VsockStream) -> Result<(), io::Error> {
+    //握手，按客户端的优先顺序选择双方都支持的分帧方式
+    let handshake = Handshake::default().framings(vec![Framing::Line, Framing::Len]);
+    let mut conn = Connection::server(stream, &handshake).await?;
+
     //接收数据
-    let request = stream.read_line().await?;
+    let request = conn.recv_frame().await?;
+    let request = String::from_utf8_lossy(&request);
     println!("server received, {}", &request);
 
     // 发送回复
     let response = format!("The server receives your message, msg: {}", &request);
     //写入数据
-    stream.send_line(response).await?;
+    conn.send_frame(response.as_bytes()).await?;
     //关闭写入流  不关闭，另一端 read 会发生阻塞
     // stream.shutdown(Shutdown::Both)?;
     // drop(stream);
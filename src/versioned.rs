@@ -0,0 +1,264 @@
+//! 版本化帧
+//! 在 send_len 的 content-length 之外携带版本、消息类型和标志位，后续扩展协议时不需要再手工约定额外的头部
+//!
+//! 帧结构（多字节字段均为大端）：
+//! `[magic 2][version 1][msg_type 1][flags 1][length 4][扩展字段][消息内容][校验值]`
+//! - version 高 4 位为主版本，低 4 位为次版本，接收方拒绝未知的主版本
+//! - length 为头部之后所有byte的长度（扩展字段 + 消息内容 + 校验值）
+//! - 扩展字段按以下顺序出现：FLAG_CHECKSUM 时 1 个byte校验算法标识，FLAG_COMPRESSED 时 1 个byte压缩算法标识
+//! - FLAG_CHECKSUM 时帧尾部为校验值，覆盖校验值之前的全部数据（包括头部）
+//!
+//! magic、version 和 length 的位置在所有版本中保持不变，接收方总是可以完整读取一帧后再判断版本
+
+use std::borrow::Cow;
+#[cfg(any(feature = "crc32", feature = "xxhash"))]
+use crate::checksum::Checksum;
+use crate::compress::{Compression, CompressionConfig};
+use crate::error::Error;
+use crate::frame::LengthPrefixConfig;
+
+/// 帧开头的固定标识
+pub const MAGIC: [u8; 2] = *b"TD";
+/// 当前主版本
+pub const VERSION_MAJOR: u8 = 1;
+/// 当前次版本
+pub const VERSION_MINOR: u8 = 0;
+/// 固定头部的byte数
+pub const HEADER_LEN: usize = 9;
+/// 默认的最大帧长度（包含头部）
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 16 * 1024 * 1024;
+
+/// 消息内容经过压缩，扩展字段中带压缩算法标识
+pub const FLAG_COMPRESSED: u8 = 0x01;
+/// 帧尾部带校验值，扩展字段中带校验算法标识
+pub const FLAG_CHECKSUM: u8 = 0x02;
+/// 该类型消息的最后一帧
+pub const FLAG_END_OF_STREAM: u8 = 0x04;
+const KNOWN_FLAGS: u8 = FLAG_COMPRESSED | FLAG_CHECKSUM | FLAG_END_OF_STREAM;
+
+/// 固定头部
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub version: u8,
+    pub msg_type: u8,
+    pub flags: u8,
+    /// 头部之后的byte数
+    pub length: u32,
+}
+
+impl FrameHeader {
+    pub fn major(&self) -> u8 {
+        self.version >> 4
+    }
+
+    pub fn minor(&self) -> u8 {
+        self.version & 0x0f
+    }
+
+    pub fn encode(&self, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(&MAGIC);
+        dst.push(self.version);
+        dst.push(self.msg_type);
+        dst.push(self.flags);
+        dst.extend_from_slice(&self.length.to_be_bytes());
+    }
+
+    /// 解析并检查头部
+    /// magic 不一致或者有未知的标志位时返回 Error::InvalidHeader，主版本不支持时返回 Error::UnsupportedVersion
+    pub fn decode(src: &[u8]) -> Result<FrameHeader, Error> {
+        if src.len() < HEADER_LEN {
+            return Err(Error::InvalidLength("frame shorter than header"));
+        }
+        if src[..2] != MAGIC {
+            return Err(Error::InvalidHeader("bad magic"));
+        }
+        let header = FrameHeader {
+            version: src[2],
+            msg_type: src[3],
+            flags: src[4],
+            length: u32::from_be_bytes([src[5], src[6], src[7], src[8]]),
+        };
+        if header.major() != VERSION_MAJOR {
+            return Err(Error::UnsupportedVersion { major: header.major(), minor: header.minor() });
+        }
+        if header.flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::InvalidHeader("unknown flags"));
+        }
+        Ok(header)
+    }
+}
+
+/// 一条版本化消息
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Frame {
+    /// 消息类型，由调用方自行约定
+    pub msg_type: u8,
+    /// 是否为该类型消息的最后一帧
+    pub end_of_stream: bool,
+    /// 消息内容（解压、去掉校验值之后）
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(msg_type: u8, payload: Vec<u8>) -> Self {
+        Frame { msg_type, end_of_stream: false, payload }
+    }
+
+    pub fn end_of_stream(mut self) -> Self {
+        self.end_of_stream = true;
+        self
+    }
+}
+
+/// 编码后的帧，分为三段，发送时用 write_vectored 一起发送
+pub(crate) struct EncodedFrame<'a> {
+    /// 头部（含扩展字段）
+    pub head: Vec<u8>,
+    /// 消息内容（压缩后或原样）
+    pub payload: Cow<'a, [u8]>,
+    /// 校验值，没有时为空
+    pub trailer: Vec<u8>,
+}
+
+/// 版本化帧的收发设置
+/// 发送时按设置压缩、附加校验值；接收时按帧中的标志位处理，compression 只用于限制解压后的长度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameOptions {
+    pub compression: Option<CompressionConfig>,
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    pub checksum: Option<Checksum>,
+    /// 整个帧（包含头部）允许的最大byte数，在分配内存之前检查
+    pub max_frame_length: usize,
+}
+
+impl Default for FrameOptions {
+    fn default() -> Self {
+        FrameOptions {
+            compression: None,
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            checksum: None,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+        }
+    }
+}
+
+impl FrameOptions {
+    pub fn compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    #[cfg(any(feature = "crc32", feature = "xxhash"))]
+    pub fn checksum(mut self, checksum: Checksum) -> Self {
+        self.checksum = Some(checksum);
+        self
+    }
+
+    pub fn max_frame_length(mut self, max: usize) -> Self {
+        self.max_frame_length = max;
+        self
+    }
+
+    /// 按 length 字段读取整个帧（不去掉头部）的格式，可用于 read_len_with 和 LengthPrefixCodec
+    pub fn length_config(&self) -> LengthPrefixConfig {
+        LengthPrefixConfig {
+            signed: false,
+            length_field_offset: HEADER_LEN - 4,
+            initial_bytes_to_strip: Some(0),
+            max_frame_length: Some(self.max_frame_length),
+            ..LengthPrefixConfig::default()
+        }
+    }
+
+    /// 编码为 头部（含扩展字段）、消息内容、校验值 三段
+    pub(crate) fn encode_parts<'a>(&self, frame: &'a Frame) -> Result<EncodedFrame<'a>, Error> {
+        let mut flags = 0;
+        if frame.end_of_stream {
+            flags |= FLAG_END_OF_STREAM;
+        }
+        let mut extensions = vec![];
+        #[cfg(any(feature = "crc32", feature = "xxhash"))]
+        let trailer_len = match self.checksum {
+            Some(checksum) => {
+                flags |= FLAG_CHECKSUM;
+                extensions.push(checksum.id());
+                checksum.size()
+            }
+            None => 0,
+        };
+        #[cfg(not(any(feature = "crc32", feature = "xxhash")))]
+        let trailer_len = 0;
+        let payload = match &self.compression {
+            Some(config) => {
+                let (compression, payload) = config.compress(&frame.payload)?;
+                //小于阈值没有压缩时不设置标志位
+                if compression != Compression::None {
+                    flags |= FLAG_COMPRESSED;
+                    extensions.push(compression.id());
+                }
+                payload
+            }
+            None => Cow::Borrowed(frame.payload.as_slice()),
+        };
+        let length = u32::try_from(extensions.len() + payload.len() + trailer_len)
+            .map_err(|_| Error::InvalidLength("content-length out of range"))?;
+        let header = FrameHeader {
+            version: VERSION_MAJOR << 4 | VERSION_MINOR,
+            msg_type: frame.msg_type,
+            flags,
+            length,
+        };
+        let mut head = Vec::with_capacity(HEADER_LEN + extensions.len());
+        header.encode(&mut head);
+        head.extend_from_slice(&extensions);
+        #[cfg(any(feature = "crc32", feature = "xxhash"))]
+        if let Some(checksum) = self.checksum {
+            let trailer = checksum.encode_value(checksum.compute_parts(&[&head, &payload]));
+            return Ok(EncodedFrame { head, payload, trailer });
+        }
+        Ok(EncodedFrame { head, payload, trailer: vec![] })
+    }
+
+    /// 编码为完整的帧
+    pub fn encode(&self, frame: &Frame) -> Result<Vec<u8>, Error> {
+        let EncodedFrame { mut head, payload, trailer } = self.encode_parts(frame)?;
+        head.extend_from_slice(&payload);
+        head.extend_from_slice(&trailer);
+        Ok(head)
+    }
+
+    /// 解析完整的帧（包含头部），校验并解压
+    pub fn decode(&self, mut src: Vec<u8>) -> Result<Frame, Error> {
+        let header = FrameHeader::decode(&src)?;
+        if src.len() - HEADER_LEN != header.length as usize {
+            return Err(Error::InvalidLength("frame length does not match header"));
+        }
+        //消息内容之前的byte数
+        let pos = if header.flags & FLAG_CHECKSUM != 0 {
+            #[cfg(any(feature = "crc32", feature = "xxhash"))]
+            {
+                let id = *src.get(HEADER_LEN).ok_or(Error::InvalidLength("frame shorter than header"))?;
+                let checksum = Checksum::from_id(id).ok_or(Error::InvalidHeader("unsupported checksum"))?;
+                src = checksum.verify(src)?;
+                HEADER_LEN + 1
+            }
+            #[cfg(not(any(feature = "crc32", feature = "xxhash")))]
+            return Err(Error::InvalidHeader("unsupported checksum"));
+        } else {
+            HEADER_LEN
+        };
+        if src.len() < pos {
+            return Err(Error::InvalidLength("frame shorter than header"));
+        }
+        let payload = if header.flags & FLAG_COMPRESSED != 0 {
+            self.compression.unwrap_or_default().decompress(&src[pos..])?
+        } else {
+            src.split_off(pos)
+        };
+        Ok(Frame {
+            msg_type: header.msg_type,
+            end_of_stream: header.flags & FLAG_END_OF_STREAM != 0,
+            payload,
+        })
+    }
+}
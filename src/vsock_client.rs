@@ -1,13 +1,12 @@
 // client.rs
 
 use std::io;
-use tokio::net::UnixStream;
 use tokio_vsock::VsockStream;
-use tcp::vsock::SocketAsyncRecvTrait;
+use tcp::vsock::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
-    let stream = UnixStream::connect("vsock://2:5000").await?;
+    let mut stream = VsockStream::connect(2, 5000).await?;
     println!("Connected to server: {:?}", stream);
 
     //发送数据
@@ -1,13 +1,12 @@
 // server.rs
 
 use std::io;
-use tokio::net::UnixListener;
-use tokio_vsock::VsockStream;
+use tokio_vsock::{VsockListener, VsockStream};
 use tcp::vsock::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
-    let listener = UnixListener::bind("vsock://2:5000").await?;
+    let mut listener = VsockListener::bind(2, 5000)?;
 
     println!("Server started, waiting for connections...");
 
@@ -17,7 +16,9 @@ async fn main() -> io::Result<()> {
             println!("New client connected: {:?}", socket);
             // Handle client connection and data here
             //处理数据
-            process_data(stream).await?;
+            if let Err(error) = process_data(socket).await {
+                println!("处理数据错误：{:?}", error);
+            }
         });
     }
 }
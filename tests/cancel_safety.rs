@@ -0,0 +1,138 @@
+//! MessageReader 接收方法的取消安全测试
+//! 在 tokio::select! 中让接收方法输掉竞争，随后补齐数据再次调用，消息必须完整且不错位
+
+use std::time::Duration;
+use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+use tokio::time::sleep;
+use tcp::frame::DelimiterConfig;
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+
+/// 编码后的 send_len 帧
+async fn len_frame(msg: &[u8]) -> Vec<u8> {
+    let mut frame = vec![];
+    frame.send_len_bytes(msg).await.unwrap();
+    frame
+}
+
+/// 写入 data 并让出执行权，确保对端已经读到这部分数据
+async fn write_part(writer: &mut DuplexStream, data: &[u8]) {
+    writer.write_all(data).await.unwrap();
+    tokio::task::yield_now().await;
+}
+
+#[tokio::test]
+async fn read_len_cancelled_mid_header() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let frame = len_frame(b"hello").await;
+
+    write_part(&mut writer, &frame[..2]).await;
+    tokio::select! {
+        _ = reader.read_len() => panic!("frame should not be complete"),
+        _ = sleep(Duration::from_millis(20)) => {}
+    }
+    assert_eq!(reader.buffer(), &frame[..2]);
+
+    write_part(&mut writer, &frame[2..]).await;
+    assert_eq!(reader.read_len().await.unwrap(), "hello");
+}
+
+#[tokio::test]
+async fn read_len_cancelled_mid_body() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let mut data = len_frame(b"first message").await;
+    data.extend(len_frame(b"second").await);
+
+    write_part(&mut writer, &data[..9]).await;
+    tokio::select! {
+        _ = reader.read_len_bytes() => panic!("frame should not be complete"),
+        _ = sleep(Duration::from_millis(20)) => {}
+    }
+
+    write_part(&mut writer, &data[9..]).await;
+    assert_eq!(reader.read_len_bytes().await.unwrap(), b"first message");
+    assert_eq!(reader.read_len_bytes().await.unwrap(), b"second");
+}
+
+#[tokio::test]
+async fn read_line_cancelled_before_delimiter() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+
+    write_part(&mut writer, b"line one\n").await;
+    tokio::select! {
+        _ = reader.read_line() => panic!("delimiter is incomplete"),
+        _ = sleep(Duration::from_millis(20)) => {}
+    }
+
+    write_part(&mut writer, b"\nline two\n\n").await;
+    assert_eq!(reader.read_line().await.unwrap(), "line one");
+    assert_eq!(reader.read_line().await.unwrap(), "line two");
+}
+
+#[tokio::test]
+async fn read_delimited_cancelled_inside_escape() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let config = DelimiterConfig::line().escape(b'\\');
+
+    let mut data = vec![];
+    data.send_delimited(b"a\nb", &config).await.unwrap();
+    //在转义字符之后取消
+    let split = data.iter().position(|&b| b == b'\\').unwrap() + 1;
+    write_part(&mut writer, &data[..split]).await;
+    tokio::select! {
+        _ = reader.read_delimited(&config) => panic!("frame should not be complete"),
+        _ = sleep(Duration::from_millis(20)) => {}
+    }
+
+    write_part(&mut writer, &data[split..]).await;
+    assert_eq!(reader.read_delimited(&config).await.unwrap(), b"a\nb");
+}
+
+#[tokio::test]
+async fn timeout_then_retry() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let frame = len_frame(b"slow peer").await;
+
+    write_part(&mut writer, &frame[..6]).await;
+    let result = tokio::time::timeout(Duration::from_millis(20), reader.read_len()).await;
+    assert!(result.is_err());
+
+    write_part(&mut writer, &frame[6..]).await;
+    assert_eq!(reader.read_len().await.unwrap(), "slow peer");
+}
+
+/// 发送端逐个byte发送，接收端每次接收都和一个立即就绪的分支竞争，消息仍然按顺序完整收到
+#[tokio::test]
+async fn repeated_cancellation_keeps_stream_in_sync() {
+    let (mut writer, reader) = duplex(64);
+    let mut reader = MessageReader::new(reader);
+    let messages: Vec<Vec<u8>> = (0..20).map(|i| vec![i as u8; i * 7]).collect();
+
+    let expected = messages.clone();
+    let sender = tokio::spawn(async move {
+        for msg in messages {
+            for byte in len_frame(&msg).await {
+                writer.write_all(&[byte]).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        }
+    });
+
+    let mut received = vec![];
+    let mut cancelled = 0;
+    while received.len() < expected.len() {
+        tokio::select! {
+            biased;
+            msg = reader.read_len_bytes() => received.push(msg.unwrap()),
+            _ = tokio::task::yield_now() => cancelled += 1,
+        }
+    }
+    sender.await.unwrap();
+    assert_eq!(received, expected);
+    assert!(cancelled > 0);
+}
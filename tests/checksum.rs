@@ -0,0 +1,91 @@
+//! 帧校验测试
+//! 每种开启的校验算法都可以通过 duplex 连接收发；数据被改动时返回 ChecksumMismatch，之后的帧仍然可以读取；
+//! std 和 tokio 的帧格式相同
+#![cfg(any(feature = "crc32", feature = "xxhash"))]
+
+use tokio::io::{duplex, AsyncWriteExt};
+use tcp::checksum::Checksum;
+use tcp::reader::MessageReader;
+use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::Error;
+
+/// 开启的校验算法
+fn checksums() -> Vec<Checksum> {
+    vec![
+        #[cfg(feature = "crc32")]
+        Checksum::Crc32,
+        #[cfg(feature = "xxhash")]
+        Checksum::XxHash64,
+    ]
+}
+
+#[cfg(feature = "crc32")]
+#[test]
+fn crc32_check_value() {
+    assert_eq!(Checksum::Crc32.compute(b"123456789"), 0xcbf43926);
+    assert_eq!(Checksum::Crc32.trailer(b"123456789"), [0xcb, 0xf4, 0x39, 0x26]);
+}
+
+#[tokio::test]
+async fn round_trip_each_checksum() {
+    for checksum in checksums() {
+        let (mut writer, reader) = duplex(64);
+        let mut reader = MessageReader::new(reader);
+        let sender = tokio::spawn(async move {
+            let sent = writer.send_len_checked(&[7u8; 1000], checksum).await.unwrap();
+            assert_eq!(sent, 4 + 1000 + checksum.size());
+            writer.send_len_checked(b"", checksum).await.unwrap();
+        });
+        assert_eq!(reader.read_len_checked(checksum).await.unwrap(), [7u8; 1000]);
+        assert_eq!(reader.read_len_checked(checksum).await.unwrap(), b"");
+        sender.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn mismatch_keeps_connection_usable() {
+    for checksum in checksums() {
+        let mut frame = vec![];
+        frame.send_len_checked(b"payload", checksum).await.unwrap();
+        frame[6] ^= 0xff;
+
+        let (mut writer, mut reader) = duplex(1024);
+        writer.write_all(&frame).await.unwrap();
+        writer.send_len_checked(b"next", checksum).await.unwrap();
+
+        match reader.read_len_checked(checksum).await {
+            Err(Error::ChecksumMismatch { expected, actual }) => {
+                assert_eq!(expected, checksum.compute(b"payload"));
+                assert_ne!(expected, actual);
+            }
+            other => panic!("{:?}: expected ChecksumMismatch, got {:?}", checksum, other),
+        }
+        assert_eq!(reader.read_len_checked(checksum).await.unwrap(), b"next");
+    }
+}
+
+#[tokio::test]
+async fn frame_shorter_than_checksum() {
+    let (mut writer, mut reader) = duplex(1024);
+    for checksum in checksums() {
+        writer.send_len_bytes(b"ab").await.unwrap();
+        assert!(matches!(reader.read_len_checked(checksum).await, Err(Error::InvalidLength(_))));
+    }
+}
+
+#[tokio::test]
+async fn std_and_tokio_interoperate() {
+    use tcp::socket::{SocketRecvTrait, SocketSendTrait};
+
+    for checksum in checksums() {
+        let mut std_frame = vec![];
+        SocketSendTrait::send_len_checked(&mut std_frame, b"from std", checksum).unwrap();
+        let mut reader = MessageReader::new(std_frame.as_slice());
+        assert_eq!(reader.read_len_checked(checksum).await.unwrap(), b"from std");
+
+        let mut tokio_frame = vec![];
+        SocketAsyncSendTrait::send_len_checked(&mut tokio_frame, b"from tokio", checksum).await.unwrap();
+        let msg = SocketRecvTrait::read_len_checked(&mut tokio_frame.as_slice(), checksum).unwrap();
+        assert_eq!(msg, b"from tokio");
+    }
+}
@@ -0,0 +1,45 @@
+//! 分块传输测试
+//! MessageReader 的缓冲区中已经读到的分块数据不会丢失，结束块之后的消息仍然可以继续接收
+
+use tokio::io::{duplex, AsyncReadExt};
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+
+#[tokio::test]
+async fn chunked_between_messages() {
+    let payload: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+    let (mut writer, reader) = duplex(4096);
+    let mut reader = MessageReader::new(reader);
+
+    let data = payload.clone();
+    let sender = tokio::spawn(async move {
+        writer.send_line_bytes(b"before").await.unwrap();
+        writer.send_chunked(&mut data.as_slice()).await.unwrap();
+        writer.send_line_bytes(b"after").await.unwrap();
+    });
+
+    assert_eq!(reader.read_line().await.unwrap(), "before");
+    let mut received = vec![];
+    let mut chunked = reader.read_chunked();
+    chunked.read_to_end(&mut received).await.unwrap();
+    assert!(chunked.is_done());
+    assert_eq!(received, payload);
+    assert_eq!(reader.read_line().await.unwrap(), "after");
+    sender.await.unwrap();
+}
+
+/// 分块数据和前一条消息在同一次读取中到达，已经在缓冲区中的部分先被读出
+#[tokio::test]
+async fn chunked_already_buffered() {
+    let mut data = vec![];
+    data.send_len_bytes(b"first").await.unwrap();
+    data.send_chunked(&mut &b"small body"[..]).await.unwrap();
+    data.send_len_bytes(b"last").await.unwrap();
+
+    let mut reader = MessageReader::new(data.as_slice());
+    assert_eq!(reader.read_len().await.unwrap(), "first");
+    let mut body = String::new();
+    reader.read_chunked().read_to_string(&mut body).await.unwrap();
+    assert_eq!(body, "small body");
+    assert_eq!(reader.read_len().await.unwrap(), "last");
+}
@@ -0,0 +1,125 @@
+//! tokio_util 编解码器测试
+//! 通过 Framed/FramedRead 收发，消息被拆分到多次读取、多条消息出现在同一次读取中时都能正确解码
+
+use bytes::{Bytes, BytesMut};
+use futures::{SinkExt, StreamExt};
+use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+use tokio_util::codec::{Decoder, Encoder, Framed, FramedRead};
+use tcp::codec::{BlankLineCodec, DelimiterCodec, EofCodec, LengthPrefixCodec};
+use tcp::frame::{DelimiterConfig, LengthPrefixConfig};
+use tcp::Error;
+
+const MESSAGES: [&[u8]; 4] = [b"first", b"", b"third message", b"\n\0\x1b end"];
+
+/// 用 codec 编码全部消息
+fn encode_all<C: Encoder<Bytes, Error = std::io::Error>>(codec: &mut C, messages: &[&[u8]]) -> Vec<u8> {
+    let mut data = BytesMut::new();
+    for msg in messages {
+        codec.encode(Bytes::copy_from_slice(msg), &mut data).unwrap();
+    }
+    data.to_vec()
+}
+
+/// 写入 data，每次只写 step 个byte，写完后关闭写通道
+async fn write_in_chunks(mut writer: DuplexStream, data: Vec<u8>, step: usize) {
+    for part in data.chunks(step) {
+        writer.write_all(part).await.unwrap();
+        tokio::task::yield_now().await;
+    }
+    writer.shutdown().await.unwrap();
+}
+
+/// 对端按 step 拆分写入 codec 编码的消息，FramedRead 读出的消息必须与发送的一致
+async fn read_split<C>(codec: C, messages: &[&[u8]], step: usize)
+where
+    C: Decoder<Item = Bytes, Error = std::io::Error> + Encoder<Bytes, Error = std::io::Error> + Clone,
+{
+    let data = encode_all(&mut codec.clone(), messages);
+    let (writer, reader) = duplex(64);
+    let sender = tokio::spawn(write_in_chunks(writer, data, step));
+    let mut frames = FramedRead::new(reader, codec);
+    for msg in messages {
+        assert_eq!(frames.next().await.unwrap().unwrap(), msg, "step {}", step);
+    }
+    assert!(frames.next().await.is_none());
+    sender.await.unwrap();
+}
+
+#[tokio::test]
+async fn length_prefix_split_frames() {
+    for config in [LengthPrefixConfig::default(), LengthPrefixConfig::u16_le(), LengthPrefixConfig::varint()] {
+        //1 个byte拆分头部，7 个byte让帧跨越读取边界，1024 个byte一次读到所有帧
+        for step in [1, 7, 1024] {
+            read_split(LengthPrefixCodec::with_config(config), &MESSAGES, step).await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn delimiter_split_frames() {
+    let config = DelimiterConfig::new(b"\r\n".to_vec()).escape(0x1b);
+    let messages: [&[u8]; 3] = [b"has \r\n inside", b"", b"end \x1b"];
+    for step in [1, 7, 1024] {
+        read_split(DelimiterCodec::new(config.clone()), &messages, step).await;
+    }
+}
+
+#[tokio::test]
+async fn blank_line_split_frames() {
+    let messages: [&[u8]; 3] = [b"first", b"second\nline", b"third"];
+    for step in [1, 7, 1024] {
+        read_split(BlankLineCodec::new(), &messages, step).await;
+    }
+}
+
+#[tokio::test]
+async fn eof_split_frames() {
+    for step in [1, 7, 1024] {
+        read_split(EofCodec::new(), &[b"the only message on the connection"], step).await;
+    }
+}
+
+#[tokio::test]
+async fn framed_round_trip() {
+    let (client, server) = duplex(64);
+    let mut client = Framed::new(client, LengthPrefixCodec::new());
+    let mut server = Framed::new(server, LengthPrefixCodec::new());
+    let echo = tokio::spawn(async move {
+        while let Some(msg) = server.next().await {
+            server.send(msg.unwrap()).await.unwrap();
+        }
+    });
+    let large = vec![7u8; 10_000];
+    for msg in [&b"ping"[..], &large] {
+        client.send(Bytes::copy_from_slice(msg)).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), msg);
+    }
+    drop(client);
+    echo.await.unwrap();
+}
+
+#[tokio::test]
+async fn truncated_frame_at_eof() {
+    let mut data = encode_all(&mut LengthPrefixCodec::new(), &[b"complete", b"cut off"]);
+    data.truncate(data.len() - 3);
+    let (writer, reader) = duplex(64);
+    tokio::spawn(write_in_chunks(writer, data, 5));
+    let mut frames = FramedRead::new(reader, LengthPrefixCodec::new());
+    assert_eq!(frames.next().await.unwrap().unwrap(), &b"complete"[..]);
+    let err = Error::from(frames.next().await.unwrap().unwrap_err());
+    assert!(matches!(err, Error::TruncatedFrame { expected: 11, received: 8 }), "{:?}", err);
+}
+
+/// 头部声明的长度来自对端，解码器不能按它一次性分配内存
+#[test]
+fn huge_header_does_not_reserve_frame_length() {
+    for (config, header) in [
+        (LengthPrefixConfig::default(), i32::MAX.to_be_bytes().to_vec()),
+        (LengthPrefixConfig::u64_be(), (u64::MAX / 2).to_be_bytes().to_vec()),
+    ] {
+        let mut codec = LengthPrefixCodec::with_config(config);
+        let mut src = BytesMut::from(&header[..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert!(src.capacity() <= 64 * 1024, "reserved {} bytes", src.capacity());
+    }
+}
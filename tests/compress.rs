@@ -0,0 +1,95 @@
+//! 按帧压缩测试
+//! 每种开启的压缩算法都可以通过 duplex 连接收发；小于阈值的消息不压缩；
+//! 解压后超长、未知的压缩算法和损坏的数据返回错误，之后连接仍然可用
+
+use tokio::io::{duplex, AsyncWriteExt};
+use tcp::compress::{Compression, CompressionConfig};
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+use tcp::Error;
+
+/// 开启的压缩算法
+fn codecs() -> Vec<Compression> {
+    vec![
+        #[cfg(feature = "zstd")]
+        Compression::Zstd,
+        #[cfg(feature = "gzip")]
+        Compression::Gzip,
+        #[cfg(feature = "lz4")]
+        Compression::Lz4,
+    ]
+}
+
+/// 长度为 len 的可压缩数据
+fn compressible(len: usize) -> Vec<u8> {
+    b"compressible text ".iter().copied().cycle().take(len).collect()
+}
+
+#[tokio::test]
+async fn round_trip_each_codec() {
+    let msg = compressible(64 * 1024);
+    for compression in codecs() {
+        let config = CompressionConfig::new(compression);
+        let (mut writer, reader) = duplex(4096);
+        let mut reader = MessageReader::new(reader);
+        let data = msg.clone();
+        let sender = tokio::spawn(async move {
+            let sent = writer.send_compressed(&data, &config).await.unwrap();
+            assert!(sent < data.len(), "{:?} did not shrink the message", compression);
+            writer.send_compressed(b"small", &config).await.unwrap();
+        });
+        assert_eq!(reader.read_compressed(&config).await.unwrap(), msg, "{:?}", compression);
+        assert_eq!(reader.read_compressed(&config).await.unwrap(), b"small");
+        sender.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn below_threshold_is_sent_uncompressed() {
+    let mut config = CompressionConfig::default().threshold(16);
+    if let Some(compression) = codecs().first() {
+        config.compression = *compression;
+    }
+    let mut frame = vec![];
+    frame.send_compressed(b"short", &config).await.unwrap();
+    assert_eq!(frame, b"\0\0\0\x06\0short");
+    assert_eq!(config.compress(b"short").unwrap().0, Compression::None);
+}
+
+#[tokio::test]
+async fn decompressed_size_limit_keeps_connection_usable() {
+    for compression in codecs() {
+        let config = CompressionConfig::new(compression).max_decompressed_size(1000);
+        let (mut writer, reader) = duplex(1024 * 1024);
+        let mut reader = MessageReader::new(reader);
+        writer.send_compressed(&vec![0u8; 100_000], &config).await.unwrap();
+        writer.send_compressed(b"next", &config).await.unwrap();
+
+        match reader.read_compressed(&config).await {
+            Err(Error::FrameTooLarge(e)) => assert_eq!((e.max, e.remaining), (1000, Some(0))),
+            other => panic!("{:?}: expected FrameTooLarge, got {:?}", compression, other),
+        }
+        assert_eq!(reader.read_compressed(&config).await.unwrap(), b"next");
+    }
+}
+
+#[tokio::test]
+async fn unknown_or_corrupt_frames_fail_to_decompress() {
+    let config = CompressionConfig::default();
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let mut frames = vec![b"\xffunknown codec".to_vec()];
+    for compression in codecs() {
+        frames.push([&[compression.id()][..], b"not compressed data"].concat());
+    }
+    for frame in &frames {
+        writer.send_len_bytes(frame).await.unwrap();
+    }
+    writer.send_compressed(b"valid", &config).await.unwrap();
+    writer.shutdown().await.unwrap();
+
+    for _ in &frames {
+        assert!(matches!(reader.read_compressed(&config).await, Err(Error::Decompress(_))));
+    }
+    assert_eq!(reader.read_compressed(&config).await.unwrap(), b"valid");
+}
@@ -0,0 +1,70 @@
+//! 结束标识分帧测试
+//! 精确匹配多byte结束标识，设置转义字符后消息内容中可以包含结束标识和转义字符本身
+
+use tokio::io::{duplex, AsyncWriteExt};
+use tcp::frame::DelimiterConfig;
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+
+#[test]
+fn escape_round_trip() {
+    let config = DelimiterConfig::crlf().escape(b'\\');
+    let msg = b"a\r\nb\\c\rd";
+    let escaped = config.escape_payload(msg);
+    assert_eq!(&escaped[..], b"a\\\r\nb\\\\c\\\rd");
+    assert_eq!(config.find_delimiter(&escaped, 0), Err(escaped.len()));
+    assert_eq!(&config.unescape_payload(&escaped)[..], msg);
+    //没有需要转义的byte时不拷贝
+    assert!(matches!(config.escape_payload(b"plain"), std::borrow::Cow::Borrowed(_)));
+}
+
+#[tokio::test]
+async fn escaped_messages_over_duplex() {
+    let config = DelimiterConfig::nul().escape(0x1b);
+    let messages: [&[u8]; 4] = [b"no escape", b"\0\0", b"\x1b", b"mixed\0\x1b\0end"];
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    for msg in messages {
+        writer.send_delimited(msg, &config).await.unwrap();
+    }
+    for msg in messages {
+        assert_eq!(reader.read_delimited(&config).await.unwrap(), msg);
+    }
+}
+
+/// 转义字符和结束标识被拆分在两次读取之间
+#[tokio::test]
+async fn escape_split_across_reads() {
+    let config = DelimiterConfig::http_header().escape(b'\\');
+    let mut data = vec![];
+    data.send_delimited(b"head\r\n\r\nbody", &config).await.unwrap();
+    data.send_delimited(b"second", &config).await.unwrap();
+
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let sender = tokio::spawn(async move {
+        for byte in data {
+            writer.write_all(&[byte]).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+    });
+    assert_eq!(reader.read_delimited(&config).await.unwrap(), b"head\r\n\r\nbody");
+    assert_eq!(reader.read_delimited(&config).await.unwrap(), b"second");
+    sender.await.unwrap();
+}
+
+#[tokio::test]
+async fn exact_match_and_keep_delimiter() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    //单独的 \r\n 不是 \r\n\r\n
+    writer.write_all(b"a\r\nb\r\n\r\nc\r\n\r\n").await.unwrap();
+    assert_eq!(reader.read_delimited(&DelimiterConfig::http_header()).await.unwrap(), b"a\r\nb");
+    let keep = DelimiterConfig::http_header().keep_delimiter(true);
+    assert_eq!(reader.read_delimited(&keep).await.unwrap(), b"c\r\n\r\n");
+
+    //对端关闭连接时剩余数据作为最后一条消息
+    writer.write_all(b"tail").await.unwrap();
+    drop(writer);
+    assert_eq!(reader.read_delimited(&DelimiterConfig::line()).await.unwrap(), b"tail");
+}
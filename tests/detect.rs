@@ -0,0 +1,110 @@
+//! 分帧方式识别测试
+//! 对端按各种方式发送消息（每次只写 1 个byte）时都能识别出分帧方式，查看过的数据不会丢失
+
+use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+use tcp::detect::{detect, DetectConfig, DetectedFraming};
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+use tcp::versioned::{Frame, FrameOptions};
+use tcp::Error;
+
+/// 写入 data，每次只写 1 个byte
+async fn write_slowly(writer: &mut DuplexStream, data: &[u8]) {
+    for b in data {
+        writer.write_all(&[*b]).await.unwrap();
+        tokio::task::yield_now().await;
+    }
+}
+
+/// 对端发送 data 后识别分帧方式，依次读取 expected 中的消息
+async fn detect_messages(data: Vec<u8>, config: &DetectConfig, framing: DetectedFraming, expected: &[&str]) {
+    let (mut writer, reader) = duplex(1024);
+    let sender = tokio::spawn(async move {
+        write_slowly(&mut writer, &data).await;
+        writer.shutdown().await.unwrap();
+    });
+    let mut reader = detect(reader, config).await.unwrap();
+    assert_eq!(reader.framing(), framing);
+    for msg in expected {
+        assert_eq!(reader.recv().await.unwrap(), *msg);
+    }
+    sender.await.unwrap();
+}
+
+#[tokio::test]
+async fn detects_each_framing() {
+    let config = DetectConfig::default();
+
+    let mut data = vec![];
+    data.send_len_bytes(b"first").await.unwrap();
+    data.send_len_bytes(b"second").await.unwrap();
+    detect_messages(data, &config, DetectedFraming::Len, &["first", "second"]).await;
+
+    let mut data = vec![];
+    data.send_line_bytes(b"first").await.unwrap();
+    data.send_line_bytes(b"second").await.unwrap();
+    detect_messages(data, &config, DetectedFraming::Line, &["first", "second"]).await;
+
+    detect_messages(b"no delimiter".to_vec(), &config, DetectedFraming::Eof, &["no delimiter", ""]).await;
+
+    let mut data = vec![];
+    let options = FrameOptions::default();
+    data.send_versioned(&Frame::new(1, b"first".to_vec()), &options).await.unwrap();
+    data.send_versioned(&Frame::new(2, b"second".to_vec()), &options).await.unwrap();
+    detect_messages(data, &config, DetectedFraming::Versioned, &["first", "second"]).await;
+}
+
+#[tokio::test]
+async fn magic_prefix_is_consumed() {
+    let config = DetectConfig::default()
+        .prefix(*b"LEN1", DetectedFraming::Len)
+        .prefix(*b"TEXT", DetectedFraming::Line);
+
+    let mut data = b"LEN1".to_vec();
+    data.send_len_bytes(b"after prefix").await.unwrap();
+    detect_messages(data, &config, DetectedFraming::Len, &["after prefix"]).await;
+
+    detect_messages(b"TEXTline\n\n".to_vec(), &config, DetectedFraming::Line, &["line"]).await;
+}
+
+#[tokio::test]
+async fn text_fallback_after_max_sniff_len() {
+    let config = DetectConfig::default().max_sniff_len(8).text_fallback(DetectedFraming::Eof);
+    let (mut writer, reader) = duplex(1024);
+    //不关闭写通道，也没有空行
+    writer.write_all(b"some text without end").await.unwrap();
+    let mut reader = detect(reader, &config).await.unwrap();
+    assert_eq!(reader.framing(), DetectedFraming::Eof);
+    drop(writer);
+    assert_eq!(reader.recv().await.unwrap(), "some text without end");
+}
+
+#[tokio::test]
+async fn replies_in_detected_framing() {
+    let (mut client, server) = duplex(1024);
+    client.send_line_bytes(b"ping").await.unwrap();
+    let mut server = detect(server, &DetectConfig::default()).await.unwrap();
+    assert_eq!(server.recv().await.unwrap(), "ping");
+    server.send("pong".to_string()).await.unwrap();
+    assert_eq!(MessageReader::new(&mut client).read_line().await.unwrap(), "pong");
+}
+
+#[tokio::test]
+async fn closed_without_data() {
+    let (writer, reader) = duplex(1024);
+    drop(writer);
+    assert!(matches!(detect(reader, &DetectConfig::default()).await, Err(Error::PeerClosed)));
+}
+
+#[test]
+fn classify_waits_for_more_data() {
+    let config = DetectConfig::default().prefix(*b"MAGIC", DetectedFraming::Len);
+    assert_eq!(config.classify(b"MAG", false), None);
+    assert_eq!(config.classify(b"\0\0", false), None);
+    assert_eq!(config.classify(b"TD", false), None);
+    assert_eq!(config.classify(b"\0\0", true), Some((DetectedFraming::Eof, 0)));
+    assert_eq!(config.classify(b"MAGIC", false), Some((DetectedFraming::Len, 5)));
+    //文本解析出的 content-length 超过 max_frame_length
+    assert_eq!(config.classify(b"GET / HTTP/1.1", false), None);
+    assert_eq!(config.classify(b"GET /\n\n", false), Some((DetectedFraming::Line, 0)));
+}
@@ -0,0 +1,116 @@
+//! 文本编码测试
+//! GBK/GB18030/Big5 消息通过 *_encoded 方法收发，content-length 按编码后的byte数计算；
+//! 无法表示的字符在发送前返回错误，非法数据返回带偏移量的 InvalidEncoding
+#![cfg(feature = "encoding")]
+
+use std::io::ErrorKind;
+use tokio::io::duplex;
+use tcp::reader::MessageReader;
+use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::text::{decode_text, encode_text, Encoding, InvalidEncoding, Utf8Mode, BIG5, GB18030, GBK};
+use tcp::Error;
+
+fn invalid_encoding(result: Result<String, Error>) -> InvalidEncoding {
+    match result {
+        Err(Error::InvalidEncoding(e)) => e,
+        other => panic!("expected InvalidEncoding, got {:?}", other),
+    }
+}
+
+#[test]
+fn known_byte_sequences() {
+    assert_eq!(encode_text("中文", GBK).unwrap(), &b"\xd6\xd0\xce\xc4"[..]);
+    assert_eq!(encode_text("中文", GB18030).unwrap(), &b"\xd6\xd0\xce\xc4"[..]);
+    assert_eq!(encode_text("中文", BIG5).unwrap(), &b"\xa4\xa4\xa4\xe5"[..]);
+    //GB18030 用 4 个byte表示 BMP 之外的字符
+    assert_eq!(encode_text("😀", GB18030).unwrap(), &b"\x94\x39\xfc\x36"[..]);
+    assert_eq!(decode_text(b"\xa4\xa4\xa4\xe5".to_vec(), BIG5, Utf8Mode::Strict).unwrap(), "中文");
+}
+
+#[tokio::test]
+async fn round_trip_each_encoding() {
+    let cases: [(&'static Encoding, &str); 3] = [
+        (GBK, "GBK 中文消息"),
+        (GB18030, "GB18030 中文消息 😀"),
+        (BIG5, "Big5 繁體中文"),
+    ];
+    for (encoding, msg) in cases {
+        let (mut writer, reader) = duplex(1024);
+        let mut reader = MessageReader::new(reader);
+        writer.send_len_encoded(msg, encoding).await.unwrap();
+        writer.send_line_encoded(msg, encoding).await.unwrap();
+        writer.send_encoded(msg, encoding).await.unwrap();
+        drop(writer);
+        assert_eq!(reader.read_len_encoded(encoding).await.unwrap(), msg);
+        assert_eq!(reader.read_line_encoded(encoding).await.unwrap(), msg);
+        assert_eq!(reader.recv_encoded(encoding).await.unwrap(), msg);
+    }
+}
+
+#[tokio::test]
+async fn length_counts_encoded_bytes() {
+    let mut frame = vec![];
+    let sent = frame.send_len_encoded("中文", GBK).await.unwrap();
+    //2 个字符、UTF-8 6 个byte、GBK 4 个byte
+    assert_eq!(frame, b"\0\0\0\x04\xd6\xd0\xce\xc4");
+    assert_eq!(sent, frame.len());
+    assert_eq!(frame.as_slice().read_len_encoded(GBK).await.unwrap(), "中文");
+}
+
+#[tokio::test]
+async fn unmappable_characters() {
+    for encoding in [GBK, BIG5] {
+        let mut frame = vec![];
+        match frame.send_len_encoded("abc😀", encoding).await {
+            Err(Error::Io(e)) => {
+                assert_eq!(e.kind(), ErrorKind::InvalidInput);
+                assert!(e.to_string().contains("byte offset 3"), "{}", e);
+            }
+            other => panic!("{}: expected InvalidInput, got {:?}", encoding.name(), other),
+        }
+        //发送前失败，没有写出任何数据
+        assert!(frame.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn malformed_input() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    //在双字节字符的中间结束
+    writer.send_len_bytes(b"ab\xd6").await.unwrap();
+    //0x81 之后不是合法的第二个byte
+    writer.send_len_bytes(b"\xd6\xd0\x81\x20cd").await.unwrap();
+    writer.send_len_encoded("之后", GBK).await.unwrap();
+
+    let err = invalid_encoding(reader.read_len_encoded(GBK).await);
+    assert_eq!((err.encoding, err.offset), ("GBK", 2));
+    assert_eq!(err.into_bytes(), b"ab\xd6");
+    let err = invalid_encoding(reader.read_len_encoded(GBK).await);
+    assert_eq!(err.offset, 2);
+    assert_eq!(reader.read_len_encoded(GBK).await.unwrap(), "之后");
+
+    let err = invalid_encoding(decode_text(b"\xa4\xa4\xff".to_vec(), BIG5, Utf8Mode::Strict));
+    assert_eq!((err.encoding, err.offset), ("Big5", 2));
+}
+
+#[tokio::test]
+async fn lossy_mode_replaces_malformed_input() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    reader.set_utf8_mode(Utf8Mode::Lossy);
+    writer.send_len_bytes(b"\xd6\xd0ab\xd6").await.unwrap();
+    assert_eq!(reader.read_len_encoded(GBK).await.unwrap(), "中ab\u{fffd}");
+}
+
+#[test]
+fn std_round_trip() {
+    use tcp::socket::{SocketRecvTrait, SocketSendTrait};
+
+    let mut data = vec![];
+    SocketSendTrait::send_len_encoded(&mut data, "繁體", BIG5).unwrap();
+    SocketSendTrait::send_line_encoded(&mut data, "简体", GB18030).unwrap();
+    let mut reader = data.as_slice();
+    assert_eq!(SocketRecvTrait::read_len_encoded(&mut reader, BIG5).unwrap(), "繁體");
+    assert_eq!(SocketRecvTrait::read_line_encoded(&mut reader, GB18030).unwrap(), "简体");
+}
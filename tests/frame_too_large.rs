@@ -0,0 +1,155 @@
+//! 最大帧长度测试
+//! content-length 格式的超长帧返回 FrameTooLarge 后跳过该帧，连接上的下一条消息仍然可以正常读取；
+//! 无法确定消息边界的格式返回 remaining 为 None
+
+use bytes::BytesMut;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio_util::codec::Decoder;
+use tcp::codec::LengthPrefixCodec;
+use tcp::frame::{FrameTooLarge, LengthPrefixConfig};
+use tcp::reader::MessageReader;
+use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::Error;
+
+fn too_large(result: Result<Vec<u8>, Error>) -> FrameTooLarge {
+    match result {
+        Err(Error::FrameTooLarge(e)) => e,
+        other => panic!("expected FrameTooLarge, got {:?}", other),
+    }
+}
+
+/// 写入 data，每次只写 step 个byte
+async fn write_slowly(writer: &mut DuplexStream, data: &[u8], step: usize) {
+    for part in data.chunks(step) {
+        writer.write_all(part).await.unwrap();
+        tokio::task::yield_now().await;
+    }
+}
+
+#[tokio::test]
+async fn message_reader_skips_oversized_frame() {
+    let (mut writer, reader) = duplex(64);
+    let mut reader = MessageReader::new(reader);
+    reader.set_max_frame_length(32);
+
+    let sender = tokio::spawn(async move {
+        let mut data = vec![];
+        data.send_len_bytes(&[1u8; 300]).await.unwrap();
+        data.send_len_bytes(b"after").await.unwrap();
+        write_slowly(&mut writer, &data, 7).await;
+    });
+
+    let err = too_large(reader.read_len_bytes().await);
+    assert_eq!((err.len, err.max), (304, 32));
+    assert_eq!(err.remaining, Some(300));
+    assert_eq!(reader.read_len().await.unwrap(), "after");
+    sender.await.unwrap();
+}
+
+#[tokio::test]
+async fn read_len_with_leaves_remaining_bytes() {
+    let (mut writer, mut reader) = duplex(1024);
+    let config = LengthPrefixConfig::default().max_frame_length(16);
+    writer.send_len_bytes(&[2u8; 40]).await.unwrap();
+    writer.send_len_bytes(b"next").await.unwrap();
+
+    let err = too_large(reader.read_len_with(&config).await);
+    //只读取了头部，调用方跳过剩余的byte后可以继续读取
+    let mut skipped = vec![0u8; err.remaining.unwrap()];
+    reader.read_exact(&mut skipped).await.unwrap();
+    assert_eq!(skipped, [2u8; 40]);
+    assert_eq!(reader.read_len_with(&config).await.unwrap(), b"next");
+}
+
+#[tokio::test]
+async fn codec_discards_across_decode_calls() {
+    let mut codec = LengthPrefixCodec::with_config(LengthPrefixConfig::default().max_frame_length(8));
+    let mut data = vec![];
+    data.send_len_bytes(&[3u8; 20]).await.unwrap();
+    data.send_len_bytes(b"ok").await.unwrap();
+
+    let mut buf = BytesMut::from(&data[..10]);
+    let err = codec.decode(&mut buf).unwrap_err();
+    assert_eq!(FrameTooLarge::from_io_error(&err).unwrap().remaining, Some(20));
+    assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    buf.extend_from_slice(&data[10..]);
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), &b"ok"[..]);
+}
+
+#[tokio::test]
+async fn unbounded_framings_report_unknown_remaining() {
+    let (mut writer, mut reader) = duplex(1024);
+    writer.send_bytes(&[4u8; 100]).await.unwrap();
+    drop(writer);
+    assert_eq!(too_large(reader.recv_bytes_limit(50).await).remaining, None);
+
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    reader.set_max_frame_length(50);
+    writer.send_line_bytes(&[b'a'; 100]).await.unwrap();
+    assert_eq!(too_large(reader.read_line_bytes().await).remaining, None);
+}
+
+/// 每次调用都使用新解码器的方法，跳过超长帧的进度保存在 MessageReader 中
+#[tokio::test]
+async fn message_reader_read_len_with_skips_oversized_frame() {
+    let config = LengthPrefixConfig::varint().max_frame_length(32);
+    let (mut writer, reader) = duplex(64);
+    let mut reader = MessageReader::new(reader);
+
+    let sender = tokio::spawn(async move {
+        let mut data = vec![];
+        data.send_len_with(&[5u8; 209], &config).await.unwrap();
+        data.send_len_with(b"valid", &config).await.unwrap();
+        write_slowly(&mut writer, &data, 5).await;
+    });
+
+    assert_eq!(too_large(reader.read_len_with(&config).await).remaining, Some(209));
+    assert_eq!(reader.read_len_with(&config).await.unwrap(), b"valid");
+    sender.await.unwrap();
+}
+
+#[cfg(feature = "prost")]
+#[tokio::test]
+async fn recv_proto_skips_oversized_frame() {
+    let config = LengthPrefixConfig::varint().max_frame_length(32);
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.send_proto_with(&"x".repeat(100), &config).await.unwrap();
+    writer.send_proto_with(&"small".to_string(), &config).await.unwrap();
+
+    let result: Result<String, Error> = reader.recv_proto_with(&config).await;
+    assert!(matches!(result, Err(Error::FrameTooLarge(_))));
+    let msg: String = reader.recv_proto_with(&config).await.unwrap();
+    assert_eq!(msg, "small");
+}
+
+/// 没有调用 set_max_frame_length 时使用默认的最大长度，对端声明的超大长度不会导致分配内存或 panic
+#[tokio::test]
+async fn huge_header_rejected_by_default() {
+    use tcp::versioned::DEFAULT_MAX_FRAME_LENGTH;
+
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.write_all(&i32::MAX.to_be_bytes()).await.unwrap();
+    let err = too_large(reader.read_len_bytes().await);
+    assert_eq!((err.len, err.max), (i32::MAX as usize + 4, DEFAULT_MAX_FRAME_LENGTH));
+
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.write_all(&(u64::MAX / 2).to_be_bytes()).await.unwrap();
+    let err = too_large(reader.read_len_with(&LengthPrefixConfig::u64_be()).await);
+    assert_eq!(err.max, DEFAULT_MAX_FRAME_LENGTH);
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn connection_rejects_huge_header_by_default() {
+    use tcp::connection::Connection;
+
+    let (mut writer, reader) = duplex(1024);
+    let mut conn = Connection::new(reader);
+    writer.write_all(&i32::MAX.to_be_bytes()).await.unwrap();
+    let result: Result<String, Error> = conn.recv_msg().await;
+    assert!(matches!(result, Err(Error::FrameTooLarge(_))), "{:?}", result);
+}
@@ -0,0 +1,120 @@
+//! 连接握手测试
+//! 双方有共同的设置时按客户端的优先顺序协商成功；没有共同设置时双方都返回 Error::Handshake；
+//! 服务端回复了客户端没有提供的设置时客户端拒绝
+#![cfg(feature = "serde")]
+
+use tokio::io::{duplex, DuplexStream};
+use tcp::compress::Compression;
+use tcp::connection::Connection;
+use tcp::handshake::{self, Framing, Handshake, Settings, HANDSHAKE_MAGIC, PROTOCOL_VERSION};
+use tcp::message::Format;
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+use tcp::Error;
+
+/// 同时运行客户端和服务端握手
+async fn run(client: Handshake, server: Handshake) -> (Result<Settings, Error>, Result<Settings, Error>) {
+    let (client_stream, server_stream) = duplex(1024);
+    let server = tokio::spawn(async move {
+        handshake::server(&mut MessageReader::new(server_stream), &server).await
+    });
+    let client = handshake::client(&mut MessageReader::new(client_stream), &client).await;
+    (client, server.await.unwrap())
+}
+
+/// 服务端回复 ACCEPT 和手工构造的设置，不经过协商
+async fn hostile_server(stream: DuplexStream, version: u8, framing: u8, max: u64, compression: u8, format: u8) {
+    let mut reader = MessageReader::new(stream);
+    reader.read_len_bytes().await.unwrap();
+    let mut reply = HANDSHAKE_MAGIC.to_vec();
+    reply.extend_from_slice(&[1, version, framing]);
+    reply.extend_from_slice(&max.to_be_bytes());
+    reply.extend_from_slice(&[compression, format]);
+    reader.send_len_bytes(&reply).await.unwrap();
+}
+
+/// 握手失败的原因
+fn rejected(result: Result<Settings, Error>) -> String {
+    match result {
+        Err(Error::Handshake(reason)) => reason,
+        other => panic!("expected Handshake, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn accept_in_client_order() {
+    let client = Handshake::default()
+        .framings(vec![Framing::Versioned, Framing::Len])
+        .max_frame_length(4096);
+    let server = Handshake::default().framings(vec![Framing::Len, Framing::Versioned]);
+    let (client, server) = run(client, server).await;
+    let settings = client.unwrap();
+    assert_eq!(settings, server.unwrap());
+    assert_eq!(settings.version, PROTOCOL_VERSION);
+    assert_eq!(settings.framing, Framing::Versioned);
+    assert_eq!(settings.max_frame_length, 4096);
+    assert_eq!((settings.compression, settings.format), (Compression::None, Format::Json));
+}
+
+#[tokio::test]
+async fn line_framing_disables_compression() {
+    let client = Handshake::default().framings(vec![Framing::Line]).compressions(vec![]);
+    let server = Handshake::default().framings(vec![Framing::Line]);
+    let (client, server) = run(client, server).await;
+    assert_eq!(client.unwrap().compression, Compression::None);
+    assert_eq!(server.unwrap().framing, Framing::Line);
+}
+
+#[tokio::test]
+async fn reject_without_common_settings() {
+    let (client, server) = run(Handshake::default().versions(2, 3), Handshake::default()).await;
+    assert!(rejected(client).contains("no common protocol version"));
+    assert_eq!(rejected(server), "no common protocol version");
+
+    let (client, server) = run(Handshake::default().framings(vec![Framing::Versioned]), Handshake::default()).await;
+    assert!(rejected(client).contains("no common framing"));
+    assert_eq!(rejected(server), "no common framing");
+}
+
+#[tokio::test]
+async fn client_rejects_unoffered_settings() {
+    let handshake = Handshake::default().versions(1, 1).max_frame_length(1024);
+    let json = Format::Json.id();
+    let replies = [
+        //版本超出范围
+        (2, Framing::Len.id(), 1024, 0, json),
+        //没有提供的分帧方式
+        (1, Framing::Versioned.id(), 1024, 0, json),
+        //未知的分帧方式
+        (1, 0xff, 1024, 0, json),
+        //超过客户端的最大帧长度
+        (1, Framing::Len.id(), 1025, 0, json),
+        //没有提供的压缩算法
+        (1, Framing::Len.id(), 1024, 0xff, json),
+        //没有提供的序列化格式
+        (1, Framing::Len.id(), 1024, 0, 0xff),
+    ];
+    for (version, framing, max, compression, format) in replies {
+        let (client_stream, server_stream) = duplex(1024);
+        let server = tokio::spawn(hostile_server(server_stream, version, framing, max, compression, format));
+        let result = handshake::client(&mut MessageReader::new(client_stream), &handshake).await;
+        assert_eq!(rejected(result), "peer chose unsupported settings");
+        server.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn connection_applies_settings() {
+    let (client_stream, server_stream) = duplex(1024);
+    let handshake = Handshake::default().framings(vec![Framing::Versioned]);
+    let server_handshake = handshake.clone();
+    let server = tokio::spawn(async move {
+        let mut conn = Connection::server(server_stream, &server_handshake).await.unwrap();
+        let msg: String = conn.recv_msg().await.unwrap();
+        assert_eq!(msg, "hello");
+    });
+    let mut conn = Connection::client(client_stream, &handshake).await.unwrap();
+    assert_eq!(conn.framing(), Framing::Versioned);
+    conn.send_msg("hello").await.unwrap();
+    server.await.unwrap();
+}
@@ -0,0 +1,115 @@
+//! LengthPrefixConfig 头部格式测试
+//! 检查 Netty、varint 等头部的编码结果，并通过 duplex 连接确认 send_len_with/read_len_with 可以互相收发
+
+use tokio::io::{duplex, AsyncWriteExt};
+use tcp::frame::{LengthPrefixConfig, LengthWidth};
+use tcp::reader::MessageReader;
+use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+
+fn header(config: &LengthPrefixConfig, content_len: usize) -> Vec<u8> {
+    let mut dst = vec![];
+    config.encode_header(content_len, &mut dst).unwrap();
+    dst
+}
+
+#[test]
+fn default_header_is_big_endian_i32() {
+    let config = LengthPrefixConfig::default();
+    assert_eq!(header(&config, 0x0102), [0, 0, 1, 2]);
+    assert_eq!(config.decode_header(&[0, 0, 1, 2]).unwrap(), Some((4, 4 + 0x0102)));
+    //有符号 i32 的最高位为 1 时是非法长度
+    assert!(config.decode_header(&[0x80, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn netty_header_with_offset_and_adjustment() {
+    //2 个byte的前缀 + 2 个byte的长度，长度包含整个头部
+    let config = LengthPrefixConfig::netty(1024, 2, 2, -4, 4).unwrap();
+    assert_eq!(header(&config, 10), [0, 0, 0, 14]);
+    assert_eq!(config.decode_header(&[0, 0, 0, 14]).unwrap(), Some((4, 14)));
+    //头部未接收完整
+    assert_eq!(config.decode_header(&[0, 0, 0]).unwrap(), None);
+    assert!(LengthPrefixConfig::netty(1024, 0, 5, 0, 0).is_err());
+}
+
+#[test]
+fn varint_header() {
+    let config = LengthPrefixConfig::varint();
+    assert_eq!(config.width, LengthWidth::Varint);
+    assert_eq!(header(&config, 1), [1]);
+    assert_eq!(header(&config, 300), [0xac, 0x02]);
+    assert_eq!(config.decode_header(&[0xac, 0x02]).unwrap(), Some((2, 302)));
+    //最高位为 1 表示后面还有byte
+    assert_eq!(config.decode_header(&[0xac]).unwrap(), None);
+    assert!(config.decode_header(&[0xff; 10]).is_err());
+}
+
+#[test]
+fn content_length_out_of_range() {
+    let config = LengthPrefixConfig::netty(1024, 0, 1, 0, 1).unwrap();
+    assert_eq!(header(&config, 255), [255]);
+    assert!(config.encode_header(256, &mut vec![]).is_err());
+    assert_eq!(header(&LengthPrefixConfig::u16_le(), 0x0102), [2, 1]);
+}
+
+#[tokio::test]
+async fn round_trip_over_duplex() {
+    let configs = [
+        LengthPrefixConfig::default(),
+        LengthPrefixConfig::u16_le(),
+        LengthPrefixConfig::u64_be(),
+        LengthPrefixConfig::varint(),
+        LengthPrefixConfig::netty(1024, 2, 3, -5, 5).unwrap(),
+    ];
+    for config in configs {
+        let (mut writer, mut reader) = duplex(1024);
+        let msg = vec![7u8; 200];
+        writer.send_len_with(&msg, &config).await.unwrap();
+        writer.send_len_with(b"next", &config).await.unwrap();
+        assert_eq!(reader.read_len_with(&config).await.unwrap(), msg, "{:?}", config);
+        assert_eq!(reader.read_len_with(&config).await.unwrap(), b"next", "{:?}", config);
+    }
+}
+
+/// initial_bytes_to_strip 为 0 时返回包含头部的整个帧
+#[tokio::test]
+async fn keep_header_when_strip_is_zero() {
+    let config = LengthPrefixConfig::netty(1024, 0, 2, 0, 0).unwrap();
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::with_length_config(reader, config);
+    writer.send_len_with(b"abc", &config).await.unwrap();
+    writer.shutdown().await.unwrap();
+    assert_eq!(reader.read_len_bytes().await.unwrap(), [0, 3, b'a', b'b', b'c']);
+}
+
+/// 每次 read 之前先返回一次 ErrorKind::Interrupted，且每次只返回一个byte
+struct Interrupting<'a> {
+    data: &'a [u8],
+    interrupt: bool,
+}
+
+impl std::io::Read for Interrupting<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.interrupt = !self.interrupt;
+        if self.interrupt {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        let n = self.data.len().min(buf.len()).min(1);
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn std_reads_retry_on_interrupted() {
+    use tcp::frame::DelimiterConfig;
+    use tcp::socket::{SocketRecvTrait, SocketSendTrait};
+
+    let mut data = vec![];
+    SocketSendTrait::send_len_with(&mut data, b"varint", &LengthPrefixConfig::varint()).unwrap();
+    SocketSendTrait::send_line_bytes(&mut data, b"line").unwrap();
+    let mut reader = Interrupting { data: &data, interrupt: false };
+    assert_eq!(SocketRecvTrait::read_len_with(&mut reader, &LengthPrefixConfig::varint()).unwrap(), b"varint");
+    assert_eq!(SocketRecvTrait::read_delimited(&mut reader, &DelimiterConfig::blank_line()).unwrap(), b"line");
+}
@@ -0,0 +1,153 @@
+//! 结构化消息测试
+//! 结构体通过 send_msg/recv_msg 按 content-length 分帧收发，每种 Format 都能通过 Connection 和 *_with 方法往返；
+//! 无法反序列化的消息返回 Error::Deserialize，其中保存格式自己的错误类型，该消息之后连接仍然可用
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use tokio::io::duplex;
+use tcp::connection::Connection;
+use tcp::message::Format;
+use tcp::reader::MessageReader;
+use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::Error;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Reading {
+    sensor: String,
+    seq: u64,
+    values: Vec<f64>,
+    unit: Option<String>,
+}
+
+fn reading() -> Reading {
+    Reading {
+        sensor: "温度-01".to_string(),
+        seq: u64::MAX - 1,
+        values: vec![21.5, -3.25, 0.0],
+        unit: Some("℃".to_string()),
+    }
+}
+
+/// 取出 Error::Deserialize 中保存的原始错误
+fn deserialize_error<T: std::fmt::Debug>(result: Result<T, Error>) -> Box<dyn std::error::Error + Send + Sync> {
+    match result {
+        Err(Error::Deserialize(e)) => e,
+        other => panic!("expected Deserialize, got {:?}", other),
+    }
+}
+
+/// 两端都使用 format 的连接上收发 reading()，MessageReader 的 recv_msg_with 也能读出同一条消息
+async fn round_trip(format: Format) {
+    let (client, server) = duplex(1024);
+    let mut client = Connection::with_format(client, format);
+    let mut server = Connection::with_format(server, format);
+    client.send_msg(&reading()).await.unwrap();
+    assert_eq!(server.recv_msg::<Reading>().await.unwrap(), reading());
+    server.get_mut().send_msg_with(&reading(), format).await.unwrap();
+    assert_eq!(client.reader_mut().recv_msg_with::<Reading>(format).await.unwrap(), reading());
+}
+
+/// 依次发送截断的 reading() 和 malformed，两条都返回 Error::Deserialize，之后的消息仍然可以读取
+async fn decode_errors(format: Format, malformed: &[u8]) -> [Box<dyn std::error::Error + Send + Sync>; 2] {
+    let encoded = format.encode(&reading()).unwrap();
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.send_len_bytes(&encoded[..encoded.len() / 2]).await.unwrap();
+    writer.send_len_bytes(malformed).await.unwrap();
+    writer.send_msg_with(&reading(), format).await.unwrap();
+    let truncated = deserialize_error(reader.recv_msg_with::<Reading>(format).await);
+    let malformed = deserialize_error(reader.recv_msg_with::<Reading>(format).await);
+    assert_eq!(reader.recv_msg_with::<Reading>(format).await.unwrap(), reading());
+    [truncated, malformed]
+}
+
+#[tokio::test]
+async fn json_round_trip() {
+    round_trip(Format::Json).await;
+
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.send_msg(&reading()).await.unwrap();
+    writer.send_msg_with(&reading(), Format::Json).await.unwrap();
+    assert_eq!(reader.recv_msg::<Reading>().await.unwrap(), reading());
+    assert_eq!(reader.recv_msg_with::<Reading>(Format::Json).await.unwrap(), reading());
+
+    //流上的 Trait 方法
+    let (mut writer, mut reader) = duplex(1024);
+    writer.send_msg(&reading()).await.unwrap();
+    assert_eq!(SocketAsyncRecvTrait::recv_msg::<Reading>(&mut reader).await.unwrap(), reading());
+}
+
+#[cfg(feature = "bincode")]
+#[tokio::test]
+async fn bincode_format() {
+    round_trip(Format::Bincode).await;
+    //sensor 的长度为 1，内容不是合法的 UTF-8
+    let malformed = [&1u64.to_le_bytes()[..], b"\xff"].concat();
+    let [truncated, malformed] = decode_errors(Format::Bincode, &malformed).await;
+    let truncated = truncated.downcast_ref::<bincode::Error>().unwrap();
+    assert!(matches!(**truncated, bincode::ErrorKind::Io(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof));
+    let malformed = malformed.downcast_ref::<bincode::Error>().unwrap();
+    assert!(matches!(**malformed, bincode::ErrorKind::InvalidUtf8Encoding(_)), "{:?}", malformed);
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn msgpack_format() {
+    round_trip(Format::MessagePack).await;
+    //0xc1 是保留的标记
+    let [truncated, malformed] = decode_errors(Format::MessagePack, b"\xc1").await;
+    assert!(truncated.downcast_ref::<rmp_serde::decode::Error>().is_some(), "{:?}", truncated);
+    assert!(malformed.downcast_ref::<rmp_serde::decode::Error>().is_some(), "{:?}", malformed);
+}
+
+#[cfg(feature = "cbor")]
+#[tokio::test]
+async fn cbor_format() {
+    round_trip(Format::Cbor).await;
+    //附加信息 28 是保留值
+    let [truncated, malformed] = decode_errors(Format::Cbor, b"\x1c").await;
+    type CborError = ciborium::de::Error<std::io::Error>;
+    assert!(matches!(truncated.downcast_ref::<CborError>(), Some(CborError::Io(_))), "{:?}", truncated);
+    assert!(matches!(malformed.downcast_ref::<CborError>(), Some(CborError::Syntax(0))), "{:?}", malformed);
+}
+
+#[cfg(feature = "postcard")]
+#[tokio::test]
+async fn postcard_format() {
+    round_trip(Format::Postcard).await;
+    //seq 的 varint 超过 10 个byte
+    let malformed = [&b"\x01a"[..], &[0xff; 11]].concat();
+    let [truncated, malformed] = decode_errors(Format::Postcard, &malformed).await;
+    assert_eq!(truncated.downcast_ref::<postcard::Error>(), Some(&postcard::Error::DeserializeUnexpectedEnd));
+    assert_eq!(malformed.downcast_ref::<postcard::Error>(), Some(&postcard::Error::DeserializeBadVarint));
+}
+
+#[tokio::test]
+async fn json_is_length_prefixed() {
+    let mut frame = vec![];
+    frame.send_msg(&[1, 2, 3]).await.unwrap();
+    assert_eq!(frame, b"\0\0\0\x07[1,2,3]");
+}
+
+#[tokio::test]
+async fn json_decode_error() {
+    //字段类型不对
+    let malformed = br#"{"sensor":"a","seq":"one","values":[],"unit":null}"#;
+    let [truncated, malformed] = decode_errors(Format::Json, malformed).await;
+    assert!(truncated.downcast_ref::<serde_json::Error>().unwrap().is_eof());
+    assert!(malformed.downcast_ref::<serde_json::Error>().unwrap().is_data());
+}
+
+#[test]
+fn std_json_round_trip() {
+    use tcp::socket::{SocketRecvTrait, SocketSendTrait};
+
+    let mut data = vec![];
+    SocketSendTrait::send_msg(&mut data, &reading()).unwrap();
+    SocketSendTrait::send_len_bytes(&mut data, b"not json").unwrap();
+    let mut reader = data.as_slice();
+    assert_eq!(SocketRecvTrait::recv_msg::<Reading>(&mut reader).unwrap(), reading());
+    let err = deserialize_error(SocketRecvTrait::recv_msg::<Reading>(&mut reader));
+    assert!(err.downcast_ref::<serde_json::Error>().unwrap().is_syntax());
+}
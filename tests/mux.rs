@@ -0,0 +1,91 @@
+//! 多路复用客户端测试
+//! 服务端乱序回复时每个请求收到自己的回复；请求超时后等待表中的记录被删除，迟到的回复被丢弃；
+//! 连接关闭时等待中的请求和之后的请求返回 Error::PeerClosed
+
+use std::time::Duration;
+use tokio::io::{duplex, DuplexStream};
+use tcp::mux::MuxClient;
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+use tcp::Error;
+
+/// 读取 count 个请求后按相反的顺序回复，回复内容为 "reply to " + 请求内容
+async fn reverse_server(stream: DuplexStream, count: usize) -> MessageReader<DuplexStream> {
+    let mut reader = MessageReader::new(stream);
+    let mut requests = vec![];
+    for _ in 0..count {
+        requests.push(reader.read_tagged().await.unwrap());
+    }
+    for (id, msg) in requests.into_iter().rev() {
+        reader.send_tagged(id, &[b"reply to ", &msg[..]].concat()).await.unwrap();
+    }
+    reader
+}
+
+#[tokio::test]
+async fn out_of_order_replies() {
+    let (client, server) = duplex(1024);
+    let server = tokio::spawn(reverse_server(server, 3));
+    let client = MuxClient::new(client);
+
+    let requests = ["a", "b", "c"].map(|msg| {
+        let client = client.clone();
+        tokio::spawn(async move { client.request(msg.as_bytes()).await })
+    });
+    for (msg, request) in ["a", "b", "c"].iter().zip(requests) {
+        assert_eq!(request.await.unwrap().unwrap(), format!("reply to {}", msg).into_bytes());
+    }
+    assert_eq!(client.pending(), 0);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn timeout_removes_pending_request() {
+    let (client, server) = duplex(1024);
+    let client = MuxClient::new(client);
+    let server = tokio::spawn(async move {
+        let mut reader = MessageReader::new(server);
+        let (late_id, _) = reader.read_tagged().await.unwrap();
+        let (id, msg) = reader.read_tagged().await.unwrap();
+        //超时请求的回复迟到，客户端直接丢弃
+        reader.send_tagged(late_id, b"too late").await.unwrap();
+        reader.send_tagged(id, &msg).await.unwrap();
+        reader
+    });
+
+    let result = client.request_timeout(b"slow", Duration::from_millis(20)).await;
+    assert!(matches!(result, Err(Error::Timeout)));
+    assert_eq!(client.pending(), 0);
+    assert_eq!(client.request(b"fast").await.unwrap(), b"fast");
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn default_timeout() {
+    let (client, _server) = duplex(1024);
+    let mut client = MuxClient::new(client);
+    client.set_timeout(Some(Duration::from_millis(20)));
+    assert!(matches!(client.request(b"no reply").await, Err(Error::Timeout)));
+    assert_eq!(client.pending(), 0);
+}
+
+#[tokio::test]
+async fn connection_closed_with_pending_requests() {
+    let (client, server) = duplex(1024);
+    let client = MuxClient::new(client);
+    let server = tokio::spawn(async move {
+        let mut reader = MessageReader::new(server);
+        reader.read_tagged().await.unwrap();
+        reader.read_tagged().await.unwrap();
+        //不回复直接关闭连接
+    });
+
+    let first = client.clone();
+    let first = tokio::spawn(async move { first.request(b"first").await });
+    let second = client.request(b"second").await;
+    assert!(matches!(second, Err(Error::PeerClosed)));
+    assert!(matches!(first.await.unwrap(), Err(Error::PeerClosed)));
+    assert_eq!(client.pending(), 0);
+    assert!(matches!(client.request(b"after close").await, Err(Error::PeerClosed)));
+    server.await.unwrap();
+}
@@ -0,0 +1,64 @@
+//! 超时测试
+//! MessageReader 的超时方法经过缓冲区，超时后数据不丢失；流上的 Trait 超时方法返回 Error::Timeout
+
+use std::time::Duration;
+use tokio::io::{duplex, AsyncWriteExt};
+use tcp::frame::DelimiterConfig;
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+use tcp::timeout::{SocketAsyncRecvTimeoutTrait, SocketAsyncSendTimeoutTrait};
+use tcp::Error;
+
+const TIMEOUT: Duration = Duration::from_millis(20);
+
+#[tokio::test]
+async fn pipelined_messages_are_not_dropped() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.write_all(b"one\n\ntwo\n\n").await.unwrap();
+
+    assert_eq!(reader.read_line_timeout(TIMEOUT).await.unwrap(), "one");
+    assert_eq!(reader.read_line_timeout(TIMEOUT).await.unwrap(), "two");
+    assert!(matches!(reader.read_line_timeout(TIMEOUT).await, Err(Error::Timeout)));
+}
+
+#[tokio::test]
+async fn reader_continues_after_timeout() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let mut frame = vec![];
+    frame.send_len_bytes(b"delayed").await.unwrap();
+
+    writer.write_all(&frame[..6]).await.unwrap();
+    assert!(matches!(reader.read_len_bytes_timeout(TIMEOUT).await, Err(Error::Timeout)));
+    writer.write_all(&frame[6..]).await.unwrap();
+    let deadline = tokio::time::Instant::now() + TIMEOUT;
+    assert_eq!(reader.read_len_bytes_deadline(deadline).await.unwrap(), b"delayed");
+}
+
+#[tokio::test]
+async fn stream_variants_time_out() {
+    let (mut writer, mut reader) = duplex(1024);
+    let config = DelimiterConfig::crlf();
+    writer.send_delimited_timeout(b"ok", &config, TIMEOUT).await.unwrap();
+    assert_eq!(reader.read_delimited_timeout(&config, TIMEOUT).await.unwrap(), b"ok");
+    assert!(matches!(reader.read_delimited_timeout(&config, TIMEOUT).await, Err(Error::Timeout)));
+
+    //对端不读取时写满缓冲区后超时
+    let (mut writer, _reader) = duplex(16);
+    let result = writer.send_len_bytes_timeout(&[0u8; 64], TIMEOUT).await;
+    assert!(matches!(result, Err(Error::Timeout)));
+}
+
+/// 只有本库产生的超时还原为 Error::Timeout，操作系统的 TimedOut 保留原始错误
+#[test]
+fn os_timed_out_stays_io() {
+    use std::io::{self, ErrorKind};
+
+    let err = Error::from(io::Error::from(ErrorKind::TimedOut));
+    assert!(matches!(&err, Error::Io(e) if e.kind() == ErrorKind::TimedOut), "{:?}", err);
+
+    let err = io::Error::from(Error::Timeout);
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    assert!(matches!(Error::from(err), Error::Timeout));
+}
@@ -0,0 +1,115 @@
+//! UTF-8 校验测试
+//! 默认严格模式下非法 UTF-8 返回 InvalidUtf8，携带第一个非法byte的偏移量和原始消息，该消息之后连接仍然可用；
+//! *_lossy 方法和 Utf8Mode::Lossy 把非法数据替换成 U+FFFD
+
+use tokio::io::{duplex, AsyncWriteExt};
+use tcp::reader::MessageReader;
+use tcp::socket::{SocketAsyncRecvTrait, SocketAsyncSendTrait};
+use tcp::text::{InvalidUtf8, Utf8Mode};
+use tcp::Error;
+
+/// 中间有一个非法byte的消息
+const INVALID: &[u8] = b"abc\xffdef";
+/// 在 "中"（e4 b8 ad）的中间结束的消息
+const INCOMPLETE: &[u8] = b"x\xe4\xb8";
+
+fn invalid_utf8(result: Result<String, Error>) -> InvalidUtf8 {
+    match result {
+        Err(Error::InvalidUtf8(e)) => e,
+        other => panic!("expected InvalidUtf8, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn strict_length_prefixed() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.send_len_bytes(INVALID).await.unwrap();
+    writer.send_len_bytes(INCOMPLETE).await.unwrap();
+    writer.send_len("中文".to_string()).await.unwrap();
+
+    let err = invalid_utf8(reader.read_len().await);
+    assert_eq!((err.offset, err.error_len), (3, Some(1)));
+    assert_eq!(err.into_bytes(), INVALID);
+    let err = invalid_utf8(reader.read_len().await);
+    assert_eq!((err.offset, err.error_len), (1, None));
+    assert_eq!(err.bytes, INCOMPLETE);
+    assert_eq!(reader.read_len().await.unwrap(), "中文");
+}
+
+#[tokio::test]
+async fn strict_delimited() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.send_line_bytes(INVALID).await.unwrap();
+    writer.send_line("next".to_string()).await.unwrap();
+
+    let err = invalid_utf8(reader.read_line().await);
+    assert_eq!((err.offset, err.bytes.as_slice()), (3, INVALID));
+    assert_eq!(reader.read_line().await.unwrap(), "next");
+
+    //流上的 Trait 方法同样严格校验
+    let (mut writer, mut reader) = duplex(1024);
+    writer.send_line_bytes(INCOMPLETE).await.unwrap();
+    let err = invalid_utf8(reader.read_line().await);
+    assert_eq!((err.offset, err.error_len), (1, None));
+}
+
+#[tokio::test]
+async fn strict_until_eof() {
+    let (mut writer, reader) = duplex(1024);
+    writer.write_all(INVALID).await.unwrap();
+    drop(writer);
+    assert_eq!(invalid_utf8(MessageReader::new(reader).recv().await).offset, 3);
+}
+
+#[tokio::test]
+async fn lossy_variants_replace_invalid_bytes() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    writer.send_len_bytes(INVALID).await.unwrap();
+    writer.send_line_bytes(INCOMPLETE).await.unwrap();
+    writer.write_all(INVALID).await.unwrap();
+    drop(writer);
+
+    assert_eq!(reader.read_len_lossy().await.unwrap(), "abc\u{fffd}def");
+    assert_eq!(reader.read_line_lossy().await.unwrap(), "x\u{fffd}");
+    assert_eq!(reader.recv_lossy().await.unwrap(), "abc\u{fffd}def");
+
+    let (mut writer, mut reader) = duplex(1024);
+    writer.send_len_bytes(INVALID).await.unwrap();
+    assert_eq!(reader.read_len_lossy().await.unwrap(), "abc\u{fffd}def");
+}
+
+#[tokio::test]
+async fn lossy_reader_mode() {
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    reader.set_utf8_mode(Utf8Mode::Lossy);
+    writer.send_len_bytes(INVALID).await.unwrap();
+    writer.send_line_bytes(INCOMPLETE).await.unwrap();
+    assert_eq!(reader.read_len().await.unwrap(), "abc\u{fffd}def");
+    assert_eq!(reader.read_line().await.unwrap(), "x\u{fffd}");
+}
+
+#[test]
+fn std_strict_and_lossy() {
+    use tcp::socket::{SocketRecvTrait, SocketSendTrait};
+
+    let mut data = vec![];
+    SocketSendTrait::send_len_bytes(&mut data, INVALID).unwrap();
+    SocketSendTrait::send_len_bytes(&mut data, INVALID).unwrap();
+    let mut reader = data.as_slice();
+    assert_eq!(invalid_utf8(SocketRecvTrait::read_len(&mut reader)).offset, 3);
+    assert_eq!(SocketRecvTrait::read_len_lossy(&mut reader).unwrap(), "abc\u{fffd}def");
+}
+
+/// 转换为 io::Error 后仍然可以还原
+#[test]
+fn io_error_round_trip() {
+    let err = invalid_utf8(Utf8Mode::Strict.decode(INVALID.to_vec()));
+    let io_err = std::io::Error::from(err.clone());
+    assert_eq!(io_err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(InvalidUtf8::from_io_error(&io_err), Some(&err));
+    assert_eq!(invalid_utf8(Err(Error::from(io_err))), err);
+}
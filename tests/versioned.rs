@@ -0,0 +1,107 @@
+//! 版本化帧测试
+//! 帧头中的 magic、版本和标志位不正确时返回错误，该帧已被完整读取，之后的帧仍然可以读取；
+//! 超长帧返回 FrameTooLarge 后跳过该帧
+
+use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+use tcp::reader::MessageReader;
+use tcp::socket::SocketAsyncSendTrait;
+use tcp::versioned::{Frame, FrameOptions, FLAG_END_OF_STREAM, HEADER_LEN, MAGIC, VERSION_MAJOR};
+use tcp::Error;
+
+/// 编码后按 modify 修改的帧
+fn modified(frame: &Frame, modify: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut data = FrameOptions::default().encode(frame).unwrap();
+    modify(&mut data);
+    data
+}
+
+/// 写入 data 后再发送一个正常的帧
+async fn write_then_valid(writer: &mut DuplexStream, data: &[u8]) {
+    writer.write_all(data).await.unwrap();
+    writer.send_versioned(&Frame::new(9, b"valid".to_vec()), &FrameOptions::default()).await.unwrap();
+}
+
+#[tokio::test]
+async fn round_trip_with_options() {
+    #[allow(unused_mut)]
+    let mut options = FrameOptions::default();
+    #[cfg(feature = "crc32")]
+    {
+        options = options.checksum(tcp::checksum::Checksum::Crc32);
+    }
+    #[cfg(feature = "zstd")]
+    {
+        use tcp::compress::{Compression, CompressionConfig};
+        options = options.compression(CompressionConfig::new(Compression::Zstd).threshold(0));
+    }
+
+    let (mut writer, reader) = duplex(256);
+    let mut reader = MessageReader::new(reader);
+    let frames = vec![
+        Frame::new(1, vec![]),
+        Frame::new(2, b"payload ".repeat(500)),
+        Frame::new(3, b"last".to_vec()).end_of_stream(),
+    ];
+    let sent = frames.clone();
+    let sender = tokio::spawn(async move {
+        for frame in &sent {
+            writer.send_versioned(frame, &options).await.unwrap();
+        }
+    });
+    for frame in frames {
+        assert_eq!(reader.read_versioned(&options).await.unwrap(), frame);
+    }
+    sender.await.unwrap();
+}
+
+#[test]
+fn header_layout() {
+    let data = modified(&Frame::new(7, b"abc".to_vec()).end_of_stream(), |_| {});
+    assert_eq!(data.len(), HEADER_LEN + 3);
+    assert_eq!(data[..2], MAGIC);
+    assert_eq!(data[2] >> 4, VERSION_MAJOR);
+    assert_eq!((data[3], data[4]), (7, FLAG_END_OF_STREAM));
+    assert_eq!(data[5..9], 3u32.to_be_bytes());
+}
+
+#[tokio::test]
+async fn rejects_bad_headers() {
+    let frame = Frame::new(1, b"rejected".to_vec());
+    let (mut writer, reader) = duplex(1024);
+    let mut reader = MessageReader::new(reader);
+    let options = FrameOptions::default();
+
+    write_then_valid(&mut writer, &modified(&frame, |data| data[0] = b'X')).await;
+    assert!(matches!(reader.read_versioned(&options).await, Err(Error::InvalidHeader("bad magic"))));
+    assert_eq!(reader.read_versioned(&options).await.unwrap().payload, b"valid");
+
+    write_then_valid(&mut writer, &modified(&frame, |data| data[2] = (VERSION_MAJOR + 1) << 4 | 3)).await;
+    match reader.read_versioned(&options).await {
+        Err(Error::UnsupportedVersion { major, minor }) => assert_eq!((major, minor), (VERSION_MAJOR + 1, 3)),
+        other => panic!("expected UnsupportedVersion, got {:?}", other),
+    }
+    assert_eq!(reader.read_versioned(&options).await.unwrap().payload, b"valid");
+
+    write_then_valid(&mut writer, &modified(&frame, |data| data[4] |= 0x80)).await;
+    assert!(matches!(reader.read_versioned(&options).await, Err(Error::InvalidHeader("unknown flags"))));
+    assert_eq!(reader.read_versioned(&options).await.unwrap().payload, b"valid");
+}
+
+#[tokio::test]
+async fn skips_oversized_frame() {
+    let options = FrameOptions::default().max_frame_length(100);
+    let (mut writer, reader) = duplex(64);
+    let mut reader = MessageReader::new(reader);
+    let sender = tokio::spawn(async move {
+        let data = FrameOptions::default().encode(&Frame::new(1, vec![0u8; 200])).unwrap();
+        assert_eq!(data.len(), 209);
+        write_then_valid(&mut writer, &data).await;
+    });
+
+    match reader.read_versioned(&options).await {
+        Err(Error::FrameTooLarge(e)) => assert_eq!((e.len, e.max), (209, 100)),
+        other => panic!("expected FrameTooLarge, got {:?}", other),
+    }
+    assert_eq!(reader.read_versioned(&options).await.unwrap().payload, b"valid");
+    sender.await.unwrap();
+}